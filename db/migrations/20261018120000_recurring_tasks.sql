@@ -0,0 +1,10 @@
+-- migrate:up
+ALTER TABLE tasks
+    ADD COLUMN cron varchar(255),
+    ADD COLUMN timezone varchar(64);
+
+-- migrate:down
+
+ALTER TABLE tasks
+    DROP COLUMN cron,
+    DROP COLUMN timezone;
@@ -0,0 +1,14 @@
+-- migrate:up
+ALTER TABLE tasks
+    ADD COLUMN interval_ms bigint,
+    ADD COLUMN max_runs integer,
+    ADD COLUMN until timestamptz,
+    ADD COLUMN run_count integer NOT NULL DEFAULT 0;
+
+-- migrate:down
+
+ALTER TABLE tasks
+    DROP COLUMN interval_ms,
+    DROP COLUMN max_runs,
+    DROP COLUMN until,
+    DROP COLUMN run_count;
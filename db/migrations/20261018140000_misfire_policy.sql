@@ -0,0 +1,6 @@
+-- migrate:up
+ALTER TABLE tasks ADD COLUMN misfire_policy smallint NOT NULL DEFAULT 0;
+
+-- migrate:down
+
+ALTER TABLE tasks DROP COLUMN misfire_policy;
@@ -0,0 +1,6 @@
+-- migrate:up
+ALTER TABLE tasks ADD COLUMN attempts integer NOT NULL DEFAULT 0;
+
+-- migrate:down
+
+ALTER TABLE tasks DROP COLUMN attempts;
@@ -0,0 +1,24 @@
+-- migrate:up
+CREATE TABLE dead_tasks (
+    id bytea PRIMARY KEY,
+    exchange varchar(255),
+    routing_key varchar(255) NOT NULL,
+    run_at timestamptz NOT NULL,
+    payload bytea NOT NULL,
+    cron varchar(255),
+    timezone varchar(64),
+    interval_ms bigint,
+    max_runs integer,
+    until timestamptz,
+    run_count integer NOT NULL DEFAULT 0,
+    misfire_policy smallint NOT NULL DEFAULT 0,
+    attempts integer NOT NULL,
+    last_error text NOT NULL,
+    failed_at timestamptz NOT NULL
+);
+
+CREATE INDEX dead_tasks_failed_at_idx ON dead_tasks (failed_at);
+
+-- migrate:down
+
+DROP TABLE dead_tasks;
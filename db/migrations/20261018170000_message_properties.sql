@@ -0,0 +1,38 @@
+-- migrate:up
+ALTER TABLE tasks
+    ADD COLUMN content_type varchar(255),
+    ADD COLUMN headers jsonb,
+    ADD COLUMN message_id varchar(255),
+    ADD COLUMN correlation_id varchar(255),
+    ADD COLUMN priority smallint,
+    ADD COLUMN expiration_ms bigint,
+    ADD COLUMN delivery_mode smallint;
+
+ALTER TABLE dead_tasks
+    ADD COLUMN content_type varchar(255),
+    ADD COLUMN headers jsonb,
+    ADD COLUMN message_id varchar(255),
+    ADD COLUMN correlation_id varchar(255),
+    ADD COLUMN priority smallint,
+    ADD COLUMN expiration_ms bigint,
+    ADD COLUMN delivery_mode smallint;
+
+-- migrate:down
+
+ALTER TABLE tasks
+    DROP COLUMN content_type,
+    DROP COLUMN headers,
+    DROP COLUMN message_id,
+    DROP COLUMN correlation_id,
+    DROP COLUMN priority,
+    DROP COLUMN expiration_ms,
+    DROP COLUMN delivery_mode;
+
+ALTER TABLE dead_tasks
+    DROP COLUMN content_type,
+    DROP COLUMN headers,
+    DROP COLUMN message_id,
+    DROP COLUMN correlation_id,
+    DROP COLUMN priority,
+    DROP COLUMN expiration_ms,
+    DROP COLUMN delivery_mode;
@@ -0,0 +1,10 @@
+-- migrate:up
+ALTER TABLE tasks ADD COLUMN mandatory boolean NOT NULL DEFAULT false;
+
+ALTER TABLE dead_tasks ADD COLUMN mandatory boolean NOT NULL DEFAULT false;
+
+-- migrate:down
+
+ALTER TABLE tasks DROP COLUMN mandatory;
+
+ALTER TABLE dead_tasks DROP COLUMN mandatory;
@@ -0,0 +1,10 @@
+-- migrate:up
+ALTER TABLE tasks ADD COLUMN idempotency_key varchar(255) UNIQUE;
+
+ALTER TABLE dead_tasks ADD COLUMN idempotency_key varchar(255);
+
+-- migrate:down
+
+ALTER TABLE tasks DROP COLUMN idempotency_key;
+
+ALTER TABLE dead_tasks DROP COLUMN idempotency_key;
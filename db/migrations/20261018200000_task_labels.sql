@@ -0,0 +1,14 @@
+-- migrate:up
+ALTER TABLE tasks ADD COLUMN labels jsonb NOT NULL DEFAULT '{}';
+
+CREATE INDEX tasks_labels_idx ON tasks USING gin (labels jsonb_path_ops);
+
+ALTER TABLE dead_tasks ADD COLUMN labels jsonb NOT NULL DEFAULT '{}';
+
+-- migrate:down
+
+DROP INDEX tasks_labels_idx;
+
+ALTER TABLE tasks DROP COLUMN labels;
+
+ALTER TABLE dead_tasks DROP COLUMN labels;
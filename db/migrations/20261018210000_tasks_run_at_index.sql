@@ -0,0 +1,6 @@
+-- migrate:up
+CREATE INDEX tasks_run_at_idx ON tasks (run_at, id);
+
+-- migrate:down
+
+DROP INDEX tasks_run_at_idx;
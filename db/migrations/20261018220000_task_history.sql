@@ -0,0 +1,20 @@
+-- migrate:up
+CREATE TABLE task_history (
+    id bigserial PRIMARY KEY,
+    task_id bytea NOT NULL,
+    exchange varchar(255),
+    routing_key varchar(255) NOT NULL,
+    scheduled_at timestamptz NOT NULL,
+    attempted_at timestamptz NOT NULL,
+    attempt integer NOT NULL,
+    outcome smallint NOT NULL,
+    error text
+);
+
+CREATE INDEX task_history_task_id_idx ON task_history (task_id, id);
+
+CREATE INDEX task_history_attempted_at_idx ON task_history (attempted_at);
+
+-- migrate:down
+
+DROP TABLE task_history;
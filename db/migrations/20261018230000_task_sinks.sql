@@ -0,0 +1,14 @@
+-- migrate:up
+ALTER TABLE tasks ADD COLUMN sink varchar(255) NOT NULL DEFAULT 'amqp';
+
+ALTER TABLE dead_tasks ADD COLUMN sink varchar(255) NOT NULL DEFAULT 'amqp';
+
+ALTER TABLE task_history ADD COLUMN sink varchar(255) NOT NULL DEFAULT 'amqp';
+
+-- migrate:down
+
+ALTER TABLE tasks DROP COLUMN sink;
+
+ALTER TABLE dead_tasks DROP COLUMN sink;
+
+ALTER TABLE task_history DROP COLUMN sink;
@@ -0,0 +1,22 @@
+-- migrate:up
+ALTER TABLE tasks ADD COLUMN destination jsonb;
+
+ALTER TABLE dead_tasks ADD COLUMN destination jsonb;
+
+ALTER TABLE tasks ALTER COLUMN routing_key TYPE text;
+
+ALTER TABLE dead_tasks ALTER COLUMN routing_key TYPE text;
+
+ALTER TABLE task_history ALTER COLUMN routing_key TYPE text;
+
+-- migrate:down
+
+ALTER TABLE task_history ALTER COLUMN routing_key TYPE varchar(255);
+
+ALTER TABLE dead_tasks ALTER COLUMN routing_key TYPE varchar(255);
+
+ALTER TABLE tasks ALTER COLUMN routing_key TYPE varchar(255);
+
+ALTER TABLE tasks DROP COLUMN destination;
+
+ALTER TABLE dead_tasks DROP COLUMN destination;
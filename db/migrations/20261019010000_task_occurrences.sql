@@ -0,0 +1,6 @@
+-- migrate:up
+ALTER TABLE tasks ADD COLUMN occurrence_at timestamptz;
+
+-- migrate:down
+
+ALTER TABLE tasks DROP COLUMN occurrence_at;
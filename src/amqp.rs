@@ -1,56 +1,334 @@
-use anyhow::Result;
-use lapin::{Channel, Connection, ConnectionProperties};
-use tracing::{debug, error, info};
+use std::{cmp::min, collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use lapin::{
+    options::{BasicPublishOptions, ConfirmSelectOptions},
+    publisher_confirm::Confirmation,
+    types::{AMQPValue, FieldTable, ShortString},
+    BasicProperties, Channel, Connection, ConnectionProperties,
+};
+use prometheus::IntGauge;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{watch, Notify, RwLock},
+    time::sleep,
+};
+use tracing::{debug, error, info, warn};
 
 use crate::{
     db::DatabaseTask,
-    prometheus::metrics::{FAILED_TASKS, SUCCESSFUL_TASKS},
+    prometheus::metrics::{AMQP_CONNECTED, UNROUTABLE_TASKS},
+    protos::rpc,
+    sink::{Destination, Sink},
 };
 
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// AMQP properties published along with a task's payload
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessageProperties {
+    pub content_type: Option<String>,
+    pub headers: HashMap<String, String>,
+    pub message_id: Option<String>,
+    pub correlation_id: Option<String>,
+    pub priority: Option<i16>,
+    pub expiration_ms: Option<i64>,
+    pub delivery_mode: Option<i16>,
+}
+
+/// Publishes a task to an AMQP exchange
+#[derive(Debug, Clone, Default)]
+pub struct AmqpDestination {
+    pub exchange: Option<String>,
+    pub routing_key: String,
+    pub properties: MessageProperties,
+    /// Treat messages the broker cannot route as failed deliveries
+    pub mandatory: bool,
+}
+
 pub struct Amqp {
+    addr: String,
+    /// Connection state reported for this broker's sink
+    connected_gauge: IntGauge,
+    inner: RwLock<Option<AmqpInner>>,
+    connection_lost: Arc<Notify>,
+    connected: watch::Sender<bool>,
+}
+
+struct AmqpInner {
     connection: Connection,
     channel: Channel,
 }
 
 impl Amqp {
-    pub async fn new(addr: &str) -> Result<Self> {
-        info!("Connecting to AMQP server at {}", addr);
-        let con = Connection::connect(addr, ConnectionProperties::default()).await?;
-        let channel = con.create_channel().await?;
+    /// Connects to the broker at `addr`, reporting the connection state under the sink `name`
+    pub async fn new(name: &str, addr: &str) -> Result<Self> {
+        let connection_lost = Arc::new(Notify::new());
+        let inner = Self::connect(addr, connection_lost.clone()).await?;
+        let connected_gauge = AMQP_CONNECTED.with_label_values(&[name]);
+        connected_gauge.set(1);
         Ok(Self {
-            connection: con,
+            addr: addr.to_string(),
+            connected_gauge,
+            inner: RwLock::new(Some(inner)),
+            connection_lost,
+            connected: watch::Sender::new(true),
+        })
+    }
+
+    async fn connect(addr: &str, connection_lost: Arc<Notify>) -> Result<AmqpInner> {
+        info!("Connecting to AMQP server at {}", addr);
+        let connection = Connection::connect(addr, ConnectionProperties::default()).await?;
+        connection.on_error(move |err| {
+            error!("AMQP connection error: {:?}", err);
+            connection_lost.notify_one();
+        });
+        let channel = Self::open_channel(&connection).await?;
+        Ok(AmqpInner {
+            connection,
             channel,
         })
     }
 
-    pub async fn publish(&self, message: &DatabaseTask) -> Result<()> {
+    async fn open_channel(connection: &Connection) -> Result<Channel> {
+        let channel = connection.create_channel().await?;
+        channel
+            .confirm_select(ConfirmSelectOptions::default())
+            .await?;
+        Ok(channel)
+    }
+
+    /// Returns true if the broker is believed to be reachable
+    pub fn is_connected(&self) -> bool {
+        *self.connected.borrow()
+    }
+
+    /// Waits until the connection to the broker is (re-)established
+    pub async fn wait_connected(&self) {
+        let _ = self
+            .connected
+            .subscribe()
+            .wait_for(|connected| *connected)
+            .await;
+    }
+
+    fn set_disconnected(&self) {
+        if self.connected.send_replace(false) {
+            warn!("Lost connection to AMQP server");
+        }
+        self.connected_gauge.set(0);
+    }
+
+    fn mark_connection_lost(&self) {
+        self.set_disconnected();
+        self.connection_lost.notify_one();
+    }
+
+    /// Flags the connection as lost if the broker has gone away
+    async fn verify_connection(&self) {
+        let connected = self
+            .inner
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(|inner| inner.connection.status().connected());
+        if !connected {
+            self.mark_connection_lost();
+        }
+    }
+
+    /// Re-establishes the connection whenever it is lost, backing off exponentially between
+    /// attempts
+    pub async fn run(&self) {
+        loop {
+            self.connection_lost.notified().await;
+            let mut inner = self.inner.write().await;
+            if inner
+                .as_ref()
+                .is_some_and(|inner| inner.connection.status().connected())
+            {
+                continue;
+            }
+            self.set_disconnected();
+            *inner = None;
+            drop(inner);
+
+            let mut delay = Duration::from_secs(1);
+            loop {
+                match Self::connect(&self.addr, self.connection_lost.clone()).await {
+                    Ok(inner) => {
+                        *self.inner.write().await = Some(inner);
+                        self.connected_gauge.set(1);
+                        self.connected.send_replace(true);
+                        info!("Reconnected to AMQP server");
+                        break;
+                    }
+                    Err(e) => {
+                        error!("Failed to reconnect to AMQP server: {}", e);
+                        debug!("Retrying AMQP connection in {:?}", delay);
+                        sleep(delay).await;
+                        delay = min(delay * 2, MAX_RECONNECT_DELAY);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns a usable channel, reopening it if the broker closed it
+    async fn channel(&self) -> Result<Channel> {
+        {
+            let inner = self.inner.read().await;
+            let inner = inner
+                .as_ref()
+                .ok_or_else(|| anyhow!("AMQP connection is unavailable"))?;
+            if !inner.connection.status().connected() {
+                self.mark_connection_lost();
+                return Err(anyhow!("AMQP connection is unavailable"));
+            }
+            if inner.channel.status().connected() {
+                return Ok(inner.channel.clone());
+            }
+        }
+
+        let mut inner = self.inner.write().await;
+        let inner = inner
+            .as_mut()
+            .ok_or_else(|| anyhow!("AMQP connection is unavailable"))?;
+        if !inner.channel.status().connected() {
+            info!("Reopening AMQP channel");
+            inner.channel = Self::open_channel(&inner.connection).await?;
+        }
+        Ok(inner.channel.clone())
+    }
+
+    pub async fn publish(&self, task: &DatabaseTask, destination: &AmqpDestination) -> Result<()> {
         debug!(
             "Publishing message to exchange {:?} with routing key {}",
-            message.exchange, message.routing_key
+            destination.exchange, destination.routing_key
         );
 
-        let result = self
-            .channel
-            .basic_publish(
-                message.exchange.as_deref().unwrap_or(""),
-                &message.routing_key,
-                Default::default(),
-                &message.payload[..],
-                Default::default(),
-            )
-            .await;
-        match result {
-            Ok(_) => {
-                SUCCESSFUL_TASKS.inc();
+        match self.publish_confirmed(task, destination).await {
+            Ok(confirmation) => {
+                debug!("Published message with result {:?}", confirmation);
+                Ok(())
             }
             Err(err) => {
-                FAILED_TASKS.inc();
                 error!("Failed to publish message: {:?}", err);
-                return Err(err.into());
+                self.verify_connection().await;
+                Err(err)
             }
         }
+    }
+
+    async fn publish_confirmed(
+        &self,
+        task: &DatabaseTask,
+        destination: &AmqpDestination,
+    ) -> Result<Confirmation> {
+        let confirmation = self
+            .channel()
+            .await?
+            .basic_publish(
+                destination.exchange.as_deref().unwrap_or(""),
+                &destination.routing_key,
+                BasicPublishOptions {
+                    mandatory: destination.mandatory,
+                    ..Default::default()
+                },
+                &task.payload[..],
+                basic_properties(task, destination),
+            )
+            .await?
+            .await?;
+        match &confirmation {
+            Confirmation::Ack(Some(returned)) | Confirmation::Nack(Some(returned)) => {
+                let exchange = destination.exchange.as_deref().unwrap_or("");
+                UNROUTABLE_TASKS.with_label_values(&[exchange]).inc();
+                return Err(anyhow!(
+                    "Message was returned as unroutable: {} {}",
+                    returned.reply_code,
+                    returned.reply_text
+                ));
+            }
+            Confirmation::Nack(None) => {
+                return Err(anyhow!("Message was nacked by the broker"));
+            }
+            _ => {}
+        }
+        Ok(confirmation)
+    }
+}
+
+#[async_trait]
+impl Sink for Amqp {
+    async fn deliver(&self, task: &DatabaseTask) -> Result<()> {
+        let Destination::Amqp(destination) = &task.destination else {
+            return Err(anyhow!("Task {} is not an AMQP task", task.id));
+        };
+        self.publish(task, destination).await
+    }
 
-        debug!("Published message with result {:?}", result);
-        Ok(())
+    fn accepts(&self, destination: &Destination) -> bool {
+        matches!(destination, Destination::Amqp(_))
+    }
+
+    fn is_available(&self) -> bool {
+        self.is_connected()
+    }
+
+    async fn wait_available(&self) {
+        self.wait_connected().await;
+    }
+}
+
+/// Builds the AMQP properties for a task, using the task ID as the message ID unless one was given
+fn basic_properties(task: &DatabaseTask, destination: &AmqpDestination) -> BasicProperties {
+    let properties = &destination.properties;
+    let message_id = properties
+        .message_id
+        .clone()
+        .unwrap_or_else(|| task.id.to_string());
+    let mut result = BasicProperties::default().with_message_id(message_id.into());
+
+    if let Some(content_type) = &properties.content_type {
+        result = result.with_content_type(content_type.as_str().into());
+    }
+    if !properties.headers.is_empty() {
+        let mut headers = FieldTable::default();
+        for (key, value) in &properties.headers {
+            headers.insert(
+                ShortString::from(key.as_str()),
+                AMQPValue::LongString(value.as_str().into()),
+            );
+        }
+        result = result.with_headers(headers);
+    }
+    if let Some(correlation_id) = &properties.correlation_id {
+        result = result.with_correlation_id(correlation_id.as_str().into());
+    }
+    if let Some(priority) = properties.priority {
+        result = result.with_priority(priority as u8);
+    }
+    if let Some(expiration_ms) = properties.expiration_ms {
+        result = result.with_expiration(expiration_ms.to_string().into());
+    }
+    if let Some(delivery_mode) = properties.delivery_mode {
+        result = result.with_delivery_mode(delivery_mode as u8);
+    }
+    result
+}
+
+impl From<MessageProperties> for rpc::MessageProperties {
+    fn from(properties: MessageProperties) -> rpc::MessageProperties {
+        rpc::MessageProperties {
+            content_type: properties.content_type.unwrap_or_default(),
+            headers: properties.headers,
+            message_id: properties.message_id.unwrap_or_default(),
+            correlation_id: properties.correlation_id.unwrap_or_default(),
+            priority: properties.priority.unwrap_or_default() as u32,
+            expiration_ms: properties.expiration_ms.unwrap_or_default() as u64,
+            delivery_mode: properties.delivery_mode.unwrap_or_default() as i32,
+        }
     }
 }
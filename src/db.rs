@@ -1,56 +1,283 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
     time::Duration,
 };
 
-use anyhow::Result;
-use chrono::{DateTime, Utc};
-use sqlx::{Pool, Postgres};
-use tokio::{select, time::sleep};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use sqlx::{postgres::PgListener, Pool, Postgres, QueryBuilder, Transaction};
+use tokio::{select, sync::broadcast, time::sleep};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, warn};
 use ulid::Ulid;
 
-use crate::{amqp::Amqp, id::Id, protos::rpc::Task};
+use crate::{
+    amqp::{AmqpDestination, MessageProperties},
+    events::{EventBus, TaskEvent, TaskEventKind},
+    history::{DeliveryOutcome, TaskHistoryEntry},
+    id::Id,
+    prometheus::metrics::{BATCH_DURATION, BATCH_SIZE, DEAD_TASKS, RETRIED_TASKS},
+    protos::rpc::{self, Task},
+    recurrence::{CronSchedule, MisfirePolicy, Recurrence},
+    retry::RetryPolicy,
+    sink::{Destination, Sinks},
+};
+
+/// Postgres channel used to tell every instance that the schedule changed
+const NOTIFY_CHANNEL: &str = "tasks_changed";
 
 pub struct Database {
     pool: Pool<Postgres>,
-    amqp: Arc<Amqp>,
+    sinks: Arc<Sinks>,
+    retry_policy: RetryPolicy,
+    batch_size: i64,
+    /// How late a recurring task can be before its occurrence counts as missed
+    misfire_threshold: TimeDelta,
     token: Mutex<CancellationTokenInner>,
+    events: EventBus,
+}
+
+/// Outcome of delivering a single batch of tasks
+struct Batch {
+    claimed: usize,
+    published: i32,
+    /// Delivery stopped early because a sink became unavailable
+    paused: bool,
 }
 
 struct CancellationTokenInner(Option<CancellationToken>);
 
+#[derive(Clone)]
 pub struct DatabaseTask {
     pub id: Ulid,
-    pub exchange: Option<String>,
-    pub routing_key: String,
+    /// Name of the sink the task is delivered by
+    pub sink: String,
+    pub destination: Destination,
     pub run_at: DateTime<Utc>,
     pub payload: Vec<u8>,
+    pub cron: Option<String>,
+    pub timezone: Option<String>,
+    pub interval: Option<TimeDelta>,
+    pub max_runs: Option<i32>,
+    pub until: Option<DateTime<Utc>>,
+    pub run_count: i32,
+    pub misfire_policy: MisfirePolicy,
+    pub attempts: i32,
+    /// When the occurrence being retried was due. `run_at` holds the time of the next attempt.
+    pub occurrence_at: Option<DateTime<Utc>>,
+    pub idempotency_key: Option<String>,
+    pub labels: HashMap<String, String>,
 }
 
+#[derive(sqlx::FromRow)]
 struct DatabaseTaskTransport {
     id: Vec<u8>,
     exchange: Option<String>,
     routing_key: String,
     run_at: DateTime<Utc>,
     payload: Vec<u8>,
+    cron: Option<String>,
+    timezone: Option<String>,
+    interval_ms: Option<i64>,
+    max_runs: Option<i32>,
+    until: Option<DateTime<Utc>>,
+    run_count: i32,
+    misfire_policy: i16,
+    attempts: i32,
+    occurrence_at: Option<DateTime<Utc>>,
+    content_type: Option<String>,
+    headers: Option<serde_json::Value>,
+    message_id: Option<String>,
+    correlation_id: Option<String>,
+    priority: Option<i16>,
+    expiration_ms: Option<i64>,
+    delivery_mode: Option<i16>,
+    mandatory: bool,
+    idempotency_key: Option<String>,
+    labels: serde_json::Value,
+    sink: String,
+    destination: Option<serde_json::Value>,
+}
+
+/// Selects tasks matching all of the criteria that are set
+#[derive(Debug, Default)]
+pub struct TaskFilter {
+    pub routing_key: Option<String>,
+    pub exchange: Option<String>,
+    pub run_after: Option<DateTime<Utc>>,
+    pub run_before: Option<DateTime<Utc>>,
+    /// Only tasks having all of these labels
+    pub labels: HashMap<String, String>,
+}
+
+impl TaskFilter {
+    pub fn matches(&self, task: &DatabaseTask) -> bool {
+        if let Some(routing_key) = &self.routing_key {
+            if routing_key != task.destination.address() {
+                return false;
+            }
+        }
+        if let Some(exchange) = &self.exchange {
+            let exchange_matches = task
+                .destination
+                .amqp()
+                .is_some_and(|amqp| amqp.exchange.as_ref() == Some(exchange));
+            if !exchange_matches {
+                return false;
+            }
+        }
+        if let Some(run_after) = self.run_after {
+            if task.run_at < run_after {
+                return false;
+            }
+        }
+        if let Some(run_before) = self.run_before {
+            if task.run_at >= run_before {
+                return false;
+            }
+        }
+        self.labels
+            .iter()
+            .all(|(key, value)| task.labels.get(key) == Some(value))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routing_key.is_none()
+            && self.exchange.is_none()
+            && self.run_after.is_none()
+            && self.run_before.is_none()
+            && self.labels.is_empty()
+    }
+
+    /// Appends a WHERE clause selecting the tasks this filter matches
+    fn push_conditions(&self, query: &mut QueryBuilder<'_, Postgres>) -> Result<()> {
+        query.push(" WHERE TRUE");
+        if !self.labels.is_empty() {
+            query
+                .push(" AND labels @> ")
+                .push_bind(serde_json::to_value(&self.labels)?);
+        }
+        if let Some(routing_key) = &self.routing_key {
+            query
+                .push(" AND routing_key = ")
+                .push_bind(routing_key.clone());
+        }
+        if let Some(exchange) = &self.exchange {
+            query.push(" AND exchange = ").push_bind(exchange.clone());
+        }
+        if let Some(run_after) = self.run_after {
+            query.push(" AND run_at >= ").push_bind(run_after);
+        }
+        if let Some(run_before) = self.run_before {
+            query.push(" AND run_at < ").push_bind(run_before);
+        }
+        Ok(())
+    }
+}
+
+/// The order tasks are listed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskOrder {
+    RunAtAsc,
+    RunAtDesc,
+    CreatedAsc,
+    CreatedDesc,
+}
+
+/// The position of the last task of a page of listed tasks
+#[derive(Debug, Clone, Copy)]
+pub struct TaskCursor {
+    pub run_at: DateTime<Utc>,
+    pub id: Ulid,
+}
+
+pub struct DeadTask {
+    pub task: DatabaseTask,
+    pub last_error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+struct DeadTaskTransport {
+    id: Vec<u8>,
+    exchange: Option<String>,
+    routing_key: String,
+    run_at: DateTime<Utc>,
+    payload: Vec<u8>,
+    cron: Option<String>,
+    timezone: Option<String>,
+    interval_ms: Option<i64>,
+    max_runs: Option<i32>,
+    until: Option<DateTime<Utc>>,
+    run_count: i32,
+    misfire_policy: i16,
+    attempts: i32,
+    last_error: String,
+    failed_at: DateTime<Utc>,
+    content_type: Option<String>,
+    headers: Option<serde_json::Value>,
+    message_id: Option<String>,
+    correlation_id: Option<String>,
+    priority: Option<i16>,
+    expiration_ms: Option<i64>,
+    delivery_mode: Option<i16>,
+    mandatory: bool,
+    idempotency_key: Option<String>,
+    labels: serde_json::Value,
+    sink: String,
+    destination: Option<serde_json::Value>,
+}
+
+struct TaskHistoryTransport {
+    id: i64,
+    task_id: Vec<u8>,
+    exchange: Option<String>,
+    routing_key: String,
+    scheduled_at: DateTime<Utc>,
+    attempted_at: DateTime<Utc>,
+    attempt: i32,
+    outcome: i16,
+    error: Option<String>,
+    sink: String,
 }
 
 impl Database {
-    pub fn new(pool: Pool<Postgres>, amqp: Arc<Amqp>) -> Self {
+    pub fn new(
+        pool: Pool<Postgres>,
+        sinks: Arc<Sinks>,
+        retry_policy: RetryPolicy,
+        batch_size: i64,
+        misfire_threshold: TimeDelta,
+    ) -> Self {
         Self {
             pool,
-            amqp,
+            sinks,
+            retry_policy,
+            batch_size,
+            misfire_threshold,
             token: Mutex::new(CancellationTokenInner(None)),
+            events: EventBus::new(),
         }
     }
 
+    pub fn sinks(&self) -> &Sinks {
+        &self.sinks
+    }
+
+    /// Subscribes to lifecycle events of tasks handled by this instance
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.events.subscribe()
+    }
+
     async fn get_next_run_at(&self) -> Option<DateTime<Utc>> {
-        let query = sqlx::query!("SELECT run_at FROM tasks ORDER BY run_at ASC LIMIT 1")
-            .fetch_optional(&self.pool)
-            .await;
+        // Rows locked by another instance are being delivered by it, so ignore them
+        // Tasks for unavailable sinks are left alone until the sink is back
+        let query = sqlx::query!(
+            "SELECT run_at FROM tasks WHERE sink <> ALL($1) ORDER BY run_at ASC LIMIT 1 FOR UPDATE SKIP LOCKED",
+            &self.sinks.unavailable()
+        )
+        .fetch_optional(&self.pool)
+        .await;
         if let Ok(Some(row)) = query {
             Some(row.run_at)
         } else {
@@ -98,26 +325,183 @@ impl Database {
         }
     }
 
-    pub async fn cancel_task(&self, task_id: Ulid) -> Result<()> {
-        let task_id = task_id.to_bytes();
-        let _ = sqlx::query("DELETE FROM tasks WHERE id = $1")
-            .bind(task_id)
-            .execute(&self.pool)
-            .await;
+    /// Lists tasks matching `filter` in the given order, starting after `after`
+    pub async fn list_tasks(
+        &self,
+        filter: &TaskFilter,
+        order: TaskOrder,
+        after: Option<TaskCursor>,
+        limit: i64,
+    ) -> Result<Vec<DatabaseTask>> {
+        let mut query = QueryBuilder::new("SELECT * FROM tasks");
+        filter.push_conditions(&mut query)?;
+        if let Some(after) = after {
+            match order {
+                TaskOrder::RunAtAsc => query
+                    .push(" AND (run_at, id) > (")
+                    .push_bind(after.run_at)
+                    .push(", ")
+                    .push_bind(Id(after.id))
+                    .push(")"),
+                TaskOrder::RunAtDesc => query
+                    .push(" AND (run_at, id) < (")
+                    .push_bind(after.run_at)
+                    .push(", ")
+                    .push_bind(Id(after.id))
+                    .push(")"),
+                TaskOrder::CreatedAsc => query.push(" AND id > ").push_bind(Id(after.id)),
+                TaskOrder::CreatedDesc => query.push(" AND id < ").push_bind(Id(after.id)),
+            };
+        }
+        query.push(match order {
+            TaskOrder::RunAtAsc => " ORDER BY run_at ASC, id ASC",
+            TaskOrder::RunAtDesc => " ORDER BY run_at DESC, id DESC",
+            TaskOrder::CreatedAsc => " ORDER BY id ASC",
+            TaskOrder::CreatedDesc => " ORDER BY id DESC",
+        });
+        query.push(" LIMIT ").push_bind(limit);
+        let transport = query
+            .build_query_as::<DatabaseTaskTransport>()
+            .fetch_all(&self.pool)
+            .await?;
 
-        self.interrupt();
+        let tasks = transport
+            .into_iter()
+            .map(|t| t.try_into())
+            .filter_map(Result::ok)
+            .collect();
+        Ok(tasks)
+    }
 
-        Ok(())
+    /// Applies `update` to a task atomically, returning the updated task or `None` if the task no
+    /// longer exists. Waits for the task to finish delivering if another instance is delivering it.
+    /// Nothing is changed if `update` returns an error.
+    pub async fn update_task<F>(&self, task_id: Ulid, update: F) -> Result<Option<DatabaseTask>>
+    where
+        F: FnOnce(&mut DatabaseTask) -> Result<()>,
+    {
+        let id_bytes = task_id.to_bytes();
+        let mut tx = self.pool.begin().await?;
+        let transport = sqlx::query_as!(
+            DatabaseTaskTransport,
+            "SELECT * FROM tasks WHERE id = $1 FOR UPDATE",
+            &id_bytes
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(transport) = transport else {
+            return Ok(None);
+        };
+
+        let mut task: DatabaseTask = transport.try_into()?;
+        let previous_run_at = task.run_at;
+        update(&mut task)?;
+
+        let default = AmqpDestination::default();
+        let amqp = task.destination.amqp().unwrap_or(&default);
+        sqlx::query!(
+            "UPDATE tasks SET exchange = $2, routing_key = $3, run_at = $4, payload = $5, content_type = $6, headers = $7, message_id = $8, correlation_id = $9, priority = $10, expiration_ms = $11, delivery_mode = $12 WHERE id = $1",
+            &id_bytes,
+            amqp.exchange,
+            task.destination.address(),
+            task.run_at,
+            task.payload,
+            amqp.properties.content_type,
+            serde_json::to_value(&amqp.properties.headers)?,
+            amqp.properties.message_id,
+            amqp.properties.correlation_id,
+            amqp.properties.priority,
+            amqp.properties.expiration_ms,
+            amqp.properties.delivery_mode
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        self.events.publish(TaskEventKind::Updated, &task);
+
+        if task.run_at < previous_run_at {
+            self.notify_changed().await;
+        }
+
+        Ok(Some(task))
+    }
+
+    /// Deletes a task, returning false if it did not exist
+    pub async fn cancel_task(&self, task_id: Ulid) -> Result<bool> {
+        let cancelled = self.cancel_many_tasks(&[task_id]).await?;
+        Ok(!cancelled.is_empty())
+    }
+
+    /// Deletes tasks by ID, returning the IDs of the tasks that existed
+    pub async fn cancel_many_tasks(&self, task_ids: &[Ulid]) -> Result<Vec<Ulid>> {
+        let task_ids = task_ids
+            .iter()
+            .map(|id| id.to_bytes().to_vec())
+            .collect::<Vec<_>>();
+        let transport = sqlx::query_as!(
+            DatabaseTaskTransport,
+            "DELETE FROM tasks WHERE id = ANY($1) RETURNING *",
+            &task_ids
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        self.cancelled(transport).await
+    }
+
+    /// Deletes all tasks matching `filter`, returning their IDs
+    pub async fn cancel_tasks_matching(&self, filter: &TaskFilter) -> Result<Vec<Ulid>> {
+        let mut query = QueryBuilder::new("DELETE FROM tasks");
+        filter.push_conditions(&mut query)?;
+        query.push(" RETURNING *");
+        let transport = query
+            .build_query_as::<DatabaseTaskTransport>()
+            .fetch_all(&self.pool)
+            .await?;
+        self.cancelled(transport).await
+    }
+
+    /// Announces deleted tasks, returning their IDs
+    async fn cancelled(&self, transport: Vec<DatabaseTaskTransport>) -> Result<Vec<Ulid>> {
+        let mut cancelled = Vec::with_capacity(transport.len());
+        for transport in transport {
+            let task: DatabaseTask = transport.try_into()?;
+            self.events.publish(TaskEventKind::Cancelled, &task);
+            cancelled.push(task.id);
+        }
+
+        if !cancelled.is_empty() {
+            self.notify_changed().await;
+        }
+        Ok(cancelled)
     }
 
     async fn run_outstanding_tasks(&self) -> Result<i32> {
         debug!("Running outstanding tasks");
-        let tasks = self.get_outstanding_tasks(None).await?;
+        let mut published_tasks = 0;
+        loop {
+            let batch = self.run_batch().await?;
+            published_tasks += batch.published;
+            if batch.paused || batch.claimed < self.batch_size as usize {
+                break;
+            }
+        }
+        debug!("Published {} tasks", published_tasks);
+        Ok(published_tasks)
+    }
+
+    /// Claims and delivers up to `batch_size` due tasks in a single transaction
+    async fn run_batch(&self) -> Result<Batch> {
+        let timer = BATCH_DURATION.start_timer();
+        let mut tx = self.pool.begin().await?;
+        let tasks = self.get_outstanding_tasks(&mut tx, None).await?;
+        BATCH_SIZE.observe(tasks.len() as f64);
 
         let by_topic = tasks.iter().fold(
             HashMap::new(),
-            |mut acc: HashMap<&String, Vec<&DatabaseTask>>, task| {
-                acc.entry(&task.routing_key).or_default().push(task);
+            |mut acc: HashMap<&str, Vec<&DatabaseTask>>, task| {
+                acc.entry(task.destination.address())
+                    .or_default()
+                    .push(task);
                 acc
             },
         );
@@ -128,27 +512,325 @@ impl Database {
             by_topic.len(),
         );
 
-        let mut published_tasks = 0;
+        let mut batch = Batch {
+            claimed: tasks.len(),
+            published: 0,
+            paused: false,
+        };
+        // Announced once the outcome of the batch is committed
+        let mut events = Vec::new();
+        let mut history = Vec::new();
 
-        let mut tx = self.pool.begin().await?;
-        for (topic, tasks) in by_topic {
+        'topics: for (topic, tasks) in by_topic {
             debug!("Running tasks for topic: {}", topic);
             for task in tasks {
+                let now = Utc::now();
+                if task.should_skip(now, self.misfire_threshold) {
+                    debug!("Skipping missed run of task {}", task.id);
+                    self.complete_task(&mut tx, task, now, task.run_count)
+                        .await?;
+                    history.push((task, now, DeliveryOutcome::Skipped, None));
+                    continue;
+                }
+
                 debug!("Running task: {}", task.id);
-                if let Err(e) = self.amqp.publish(task).await {
-                    error!("Failed to publish task: {}", e);
+                if let Err(e) = self.sinks.deliver(task).await {
+                    error!("Failed to deliver task: {}", e);
+                    if self
+                        .sinks
+                        .get(&task.sink)
+                        .is_some_and(|sink| !sink.is_available())
+                    {
+                        warn!("Pausing delivery until sink {} is available", task.sink);
+                        batch.paused = true;
+                        break 'topics;
+                    }
+                    let attempts = task.attempts + 1;
+                    if attempts < self.retry_policy.max_attempts {
+                        let retry_at = now + self.retry_policy.backoff(attempts);
+                        warn!(
+                            "Retrying task {} at {} ({} of {} attempts failed)",
+                            task.id, retry_at, attempts, self.retry_policy.max_attempts
+                        );
+                        RETRIED_TASKS.inc();
+                        sqlx::query("UPDATE tasks SET run_at = $1, attempts = $2, occurrence_at = $3 WHERE id = $4")
+                            .bind(retry_at)
+                            .bind(attempts)
+                            .bind(task.occurrence())
+                            .bind(Id(task.id))
+                            .execute(&mut *tx)
+                            .await?;
+                        events.push((TaskEventKind::Failed, task));
+                        history.push((task, now, DeliveryOutcome::Failed, Some(e.to_string())));
+                        continue;
+                    }
+                    error!("Giving up on task {} after {} attempts", task.id, attempts);
+                    self.dead_letter_task(&mut tx, task, attempts, &e.to_string())
+                        .await?;
+                    events.push((TaskEventKind::DeadLettered, task));
+                    history.push((
+                        task,
+                        now,
+                        DeliveryOutcome::DeadLettered,
+                        Some(e.to_string()),
+                    ));
+                    continue;
+                } else {
+                    batch.published += 1;
+                    events.push((TaskEventKind::Delivered, task));
+                    history.push((task, now, DeliveryOutcome::Delivered, None));
                 }
 
-                sqlx::query("DELETE FROM tasks WHERE id = $1")
-                    .bind(Id(task.id))
-                    .execute(&mut *tx)
+                self.complete_task(&mut tx, task, now, task.run_count + 1)
                     .await?;
-                published_tasks += 1;
             }
         }
-        debug!("Published {} tasks", published_tasks);
+        self.record_history(&mut tx, &history).await?;
         tx.commit().await?;
-        Ok(published_tasks)
+        for (kind, task) in events {
+            self.events.publish(kind, task);
+        }
+        timer.observe_duration();
+        Ok(batch)
+    }
+
+    /// Moves a task on to its next occurrence, or removes it if it should not run again
+    async fn complete_task(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        task: &DatabaseTask,
+        now: DateTime<Utc>,
+        run_count: i32,
+    ) -> Result<()> {
+        if let Some(next_run_at) = task.next_run_at(now, run_count) {
+            debug!("Rescheduling task {} for {}", task.id, next_run_at);
+            sqlx::query("UPDATE tasks SET run_at = $1, run_count = $2, attempts = 0, occurrence_at = NULL WHERE id = $3")
+                .bind(next_run_at)
+                .bind(run_count)
+                .bind(Id(task.id))
+                .execute(&mut **tx)
+                .await?;
+        } else {
+            sqlx::query("DELETE FROM tasks WHERE id = $1")
+                .bind(Id(task.id))
+                .execute(&mut **tx)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Records the outcome of each task handled in a batch
+    async fn record_history(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        history: &[(
+            &DatabaseTask,
+            DateTime<Utc>,
+            DeliveryOutcome,
+            Option<String>,
+        )],
+    ) -> Result<()> {
+        if history.is_empty() {
+            return Ok(());
+        }
+
+        let mut task_ids = Vec::with_capacity(history.len());
+        let mut sinks = Vec::with_capacity(history.len());
+        let mut exchanges = Vec::with_capacity(history.len());
+        let mut routing_keys = Vec::with_capacity(history.len());
+        let mut scheduled_ats = Vec::with_capacity(history.len());
+        let mut attempted_ats = Vec::with_capacity(history.len());
+        let mut attempts = Vec::with_capacity(history.len());
+        let mut outcomes = Vec::with_capacity(history.len());
+        let mut errors = Vec::with_capacity(history.len());
+        for (task, attempted_at, outcome, error) in history {
+            task_ids.push(task.id.to_bytes().to_vec());
+            sinks.push(task.sink.clone());
+            exchanges.push(
+                task.destination
+                    .amqp()
+                    .and_then(|amqp| amqp.exchange.clone()),
+            );
+            routing_keys.push(task.destination.address().to_string());
+            scheduled_ats.push(task.occurrence());
+            attempted_ats.push(*attempted_at);
+            attempts.push(task.attempts + 1);
+            outcomes.push(i16::from(*outcome));
+            errors.push(error.clone());
+        }
+
+        sqlx::query!(
+            "INSERT INTO task_history (task_id, exchange, routing_key, scheduled_at, attempted_at, attempt, outcome, error, sink) \
+            SELECT * FROM UNNEST($1::bytea[], $2::varchar[], $3::text[], $4::timestamptz[], $5::timestamptz[], $6::integer[], $7::smallint[], $8::text[], $9::varchar[])",
+            &task_ids,
+            &exchanges as &[Option<String>],
+            &routing_keys,
+            &scheduled_ats,
+            &attempted_ats,
+            &attempts,
+            &outcomes,
+            &errors as &[Option<String>],
+            &sinks
+        )
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    /// Returns the recorded delivery attempts of a task, oldest first
+    pub async fn get_task_history(
+        &self,
+        task_id: Ulid,
+        after: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<TaskHistoryEntry>> {
+        let task_id = task_id.to_bytes();
+        let transport = sqlx::query_as!(
+            TaskHistoryTransport,
+            "SELECT * FROM task_history WHERE task_id = $1 AND ($2::bigint IS NULL OR id > $2) ORDER BY id ASC LIMIT $3",
+            &task_id,
+            after,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let entries = transport
+            .into_iter()
+            .map(|t| t.try_into())
+            .filter_map(Result::ok)
+            .collect();
+        Ok(entries)
+    }
+
+    pub async fn purge_task_history_before(&self, attempted_before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query!(
+            "DELETE FROM task_history WHERE attempted_at < $1",
+            attempted_before
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Moves a task that exhausted its retry budget into the dead task table. A recurring task
+    /// only dead-letters the failed occurrence, as a one-off copy, and moves on to its next one.
+    async fn dead_letter_task(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        task: &DatabaseTask,
+        attempts: i32,
+        error: &str,
+    ) -> Result<()> {
+        let id_bytes = task.id.to_bytes();
+        if task.cron.is_some() || task.interval.is_some() {
+            let dead_id = Ulid::new().to_bytes();
+            sqlx::query!(
+                "INSERT INTO dead_tasks (id, exchange, routing_key, run_at, payload, run_count, misfire_policy, content_type, headers, message_id, correlation_id, priority, expiration_ms, delivery_mode, mandatory, labels, sink, destination, attempts, last_error, failed_at) \
+                SELECT $2, exchange, routing_key, COALESCE(occurrence_at, run_at), payload, 0, misfire_policy, content_type, headers, message_id, correlation_id, priority, expiration_ms, delivery_mode, mandatory, labels, sink, destination, $3, $4, $5 FROM tasks WHERE id = $1",
+                &id_bytes,
+                &dead_id,
+                attempts,
+                error,
+                Utc::now()
+            )
+            .execute(&mut **tx)
+            .await?;
+            self.complete_task(tx, task, Utc::now(), task.run_count + 1)
+                .await?;
+            DEAD_TASKS.inc();
+            return Ok(());
+        }
+        sqlx::query!(
+            "WITH dead AS (DELETE FROM tasks WHERE id = $1 RETURNING *) \
+            INSERT INTO dead_tasks (id, exchange, routing_key, run_at, payload, cron, timezone, interval_ms, max_runs, until, run_count, misfire_policy, content_type, headers, message_id, correlation_id, priority, expiration_ms, delivery_mode, mandatory, idempotency_key, labels, sink, destination, attempts, last_error, failed_at) \
+            SELECT id, exchange, routing_key, COALESCE(occurrence_at, run_at), payload, cron, timezone, interval_ms, max_runs, until, run_count, misfire_policy, content_type, headers, message_id, correlation_id, priority, expiration_ms, delivery_mode, mandatory, idempotency_key, labels, sink, destination, $2, $3, $4 FROM dead",
+            &id_bytes,
+            attempts,
+            error,
+            Utc::now()
+        )
+        .execute(&mut **tx)
+        .await?;
+        DEAD_TASKS.inc();
+        Ok(())
+    }
+
+    pub async fn get_dead_tasks(&self, after: Option<Ulid>, limit: i64) -> Result<Vec<DeadTask>> {
+        let after = after.map(|id| id.to_bytes().to_vec());
+        let transport = sqlx::query_as!(
+            DeadTaskTransport,
+            "SELECT * FROM dead_tasks WHERE ($1::bytea IS NULL OR id > $1) ORDER BY id ASC LIMIT $2",
+            after,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let tasks = transport
+            .into_iter()
+            .map(|t| t.try_into())
+            .filter_map(Result::ok)
+            .collect();
+        Ok(tasks)
+    }
+
+    pub async fn get_dead_task(&self, task_id: Ulid) -> Option<DeadTask> {
+        let task_id = task_id.to_bytes();
+        let query = sqlx::query_as!(
+            DeadTaskTransport,
+            "SELECT * FROM dead_tasks WHERE id = $1",
+            &task_id
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        if let Ok(Some(transport)) = query {
+            transport.try_into().ok()
+        } else {
+            None
+        }
+    }
+
+    /// Moves a dead task back into the schedule, returning false if it did not exist. The task
+    /// loses its idempotency key if a live task has since been scheduled with the same key.
+    pub async fn requeue_dead_task(&self, task_id: Ulid, run_at: DateTime<Utc>) -> Result<bool> {
+        let task_id = task_id.to_bytes();
+        let result = sqlx::query!(
+            "WITH dead AS (DELETE FROM dead_tasks WHERE id = $1 RETURNING *) \
+            INSERT INTO tasks (id, exchange, routing_key, run_at, payload, cron, timezone, interval_ms, max_runs, until, run_count, misfire_policy, content_type, headers, message_id, correlation_id, priority, expiration_ms, delivery_mode, mandatory, idempotency_key, labels, sink, destination) \
+            SELECT id, exchange, routing_key, $2, payload, cron, timezone, interval_ms, max_runs, until, run_count, misfire_policy, content_type, headers, message_id, correlation_id, priority, expiration_ms, delivery_mode, mandatory, \
+            CASE WHEN EXISTS (SELECT 1 FROM tasks WHERE tasks.idempotency_key = dead.idempotency_key) THEN NULL ELSE idempotency_key END, \
+            labels, sink, destination FROM dead",
+            &task_id,
+            run_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        let requeued = result.rows_affected() > 0;
+        if requeued {
+            self.notify_changed().await;
+        }
+        Ok(requeued)
+    }
+
+    pub async fn purge_dead_tasks(&self, task_ids: &[Ulid]) -> Result<u64> {
+        let task_ids = task_ids
+            .iter()
+            .map(|id| id.to_bytes().to_vec())
+            .collect::<Vec<_>>();
+        let result = sqlx::query!("DELETE FROM dead_tasks WHERE id = ANY($1)", &task_ids)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn purge_dead_tasks_before(&self, failed_before: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM dead_tasks WHERE failed_at < $1", failed_before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
     }
 
     fn get_token(&self) -> CancellationToken {
@@ -159,22 +841,159 @@ impl Database {
         token.0.as_ref().unwrap().clone()
     }
 
-    pub async fn schedule(&self, task: &DatabaseTask) -> Result<()> {
-        let id_bytes = task.id.to_bytes();
-        let _ = sqlx::query!(
-            "INSERT INTO tasks (id, exchange, routing_key, run_at, payload) VALUES ($1, $2, $3, $4, $5)",
-            &id_bytes,
-            task.exchange,
-            task.routing_key,
-            task.run_at,
-            task.payload
+    /// Inserts a task, returning its ID. If a task with the same idempotency key already exists,
+    /// nothing is inserted and the existing task's ID is returned instead.
+    pub async fn schedule(&self, task: &DatabaseTask) -> Result<Ulid> {
+        let ids = self.schedule_many(std::slice::from_ref(task)).await?;
+        Ok(ids[0])
+    }
+
+    /// Inserts tasks in a single transaction, returning their IDs in order. Tasks whose
+    /// idempotency key is already in use are not inserted and the existing task's ID is returned
+    /// in their place. Wakes the scheduler once for the whole batch.
+    pub async fn schedule_many(&self, tasks: &[DatabaseTask]) -> Result<Vec<Ulid>> {
+        if tasks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut ids = Vec::with_capacity(tasks.len());
+        let mut sinks = Vec::with_capacity(tasks.len());
+        let mut exchanges = Vec::with_capacity(tasks.len());
+        let mut routing_keys = Vec::with_capacity(tasks.len());
+        let mut run_ats = Vec::with_capacity(tasks.len());
+        let mut payloads = Vec::with_capacity(tasks.len());
+        let mut crons = Vec::with_capacity(tasks.len());
+        let mut timezones = Vec::with_capacity(tasks.len());
+        let mut intervals = Vec::with_capacity(tasks.len());
+        let mut max_runs = Vec::with_capacity(tasks.len());
+        let mut untils = Vec::with_capacity(tasks.len());
+        let mut misfire_policies = Vec::with_capacity(tasks.len());
+        let mut content_types = Vec::with_capacity(tasks.len());
+        let mut headers = Vec::with_capacity(tasks.len());
+        let mut message_ids = Vec::with_capacity(tasks.len());
+        let mut correlation_ids = Vec::with_capacity(tasks.len());
+        let mut priorities = Vec::with_capacity(tasks.len());
+        let mut expirations = Vec::with_capacity(tasks.len());
+        let mut delivery_modes = Vec::with_capacity(tasks.len());
+        let mut mandatory = Vec::with_capacity(tasks.len());
+        let mut idempotency_keys = Vec::with_capacity(tasks.len());
+        let mut labels = Vec::with_capacity(tasks.len());
+        let mut destinations = Vec::with_capacity(tasks.len());
+        let default = AmqpDestination::default();
+        for task in tasks {
+            let amqp = task.destination.amqp().unwrap_or(&default);
+            ids.push(task.id.to_bytes().to_vec());
+            sinks.push(task.sink.clone());
+            exchanges.push(amqp.exchange.clone());
+            routing_keys.push(task.destination.address().to_string());
+            run_ats.push(task.run_at);
+            payloads.push(task.payload.clone());
+            crons.push(task.cron.clone());
+            timezones.push(task.timezone.clone());
+            intervals.push(task.interval.map(|interval| interval.num_milliseconds()));
+            max_runs.push(task.max_runs);
+            untils.push(task.until);
+            misfire_policies.push(i16::from(task.misfire_policy));
+            content_types.push(amqp.properties.content_type.clone());
+            headers.push(serde_json::to_value(&amqp.properties.headers)?);
+            message_ids.push(amqp.properties.message_id.clone());
+            correlation_ids.push(amqp.properties.correlation_id.clone());
+            priorities.push(amqp.properties.priority);
+            expirations.push(amqp.properties.expiration_ms);
+            delivery_modes.push(amqp.properties.delivery_mode);
+            mandatory.push(amqp.mandatory);
+            idempotency_keys.push(task.idempotency_key.clone());
+            labels.push(serde_json::to_value(&task.labels)?);
+            destinations.push(task.destination.to_json()?);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let inserted = sqlx::query!(
+            "INSERT INTO tasks (id, exchange, routing_key, run_at, payload, cron, timezone, interval_ms, max_runs, until, misfire_policy, content_type, headers, message_id, correlation_id, priority, expiration_ms, delivery_mode, mandatory, idempotency_key, labels, sink, destination) \
+            SELECT * FROM UNNEST($1::bytea[], $2::varchar[], $3::text[], $4::timestamptz[], $5::bytea[], $6::varchar[], $7::varchar[], $8::bigint[], $9::integer[], $10::timestamptz[], $11::smallint[], $12::varchar[], $13::jsonb[], $14::varchar[], $15::varchar[], $16::smallint[], $17::bigint[], $18::smallint[], $19::boolean[], $20::varchar[], $21::jsonb[], $22::varchar[], $23::jsonb[]) \
+            ON CONFLICT (idempotency_key) DO NOTHING RETURNING id",
+            &ids,
+            &exchanges as &[Option<String>],
+            &routing_keys,
+            &run_ats,
+            &payloads,
+            &crons as &[Option<String>],
+            &timezones as &[Option<String>],
+            &intervals as &[Option<i64>],
+            &max_runs as &[Option<i32>],
+            &untils as &[Option<DateTime<Utc>>],
+            &misfire_policies,
+            &content_types as &[Option<String>],
+            &headers,
+            &message_ids as &[Option<String>],
+            &correlation_ids as &[Option<String>],
+            &priorities as &[Option<i16>],
+            &expirations as &[Option<i64>],
+            &delivery_modes as &[Option<i16>],
+            &mandatory,
+            &idempotency_keys as &[Option<String>],
+            &labels,
+            &sinks,
+            &destinations as &[Option<serde_json::Value>]
         )
-        .execute(&self.pool)
-        .await;
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|row| row.id)
+        .collect::<HashSet<_>>();
 
-        self.interrupt();
+        let conflicting = tasks
+            .iter()
+            .filter(|task| !inserted.contains(&task.id.to_bytes()[..]))
+            .filter_map(|task| task.idempotency_key.clone())
+            .collect::<Vec<_>>();
+        let mut existing = HashMap::new();
+        if !conflicting.is_empty() {
+            let rows = sqlx::query!(
+                "SELECT id, idempotency_key FROM tasks WHERE idempotency_key = ANY($1)",
+                &conflicting
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            for row in rows {
+                if let Some(key) = row.idempotency_key {
+                    existing.insert(key, Ulid::from_bytes(row.id.as_slice().try_into()?));
+                }
+            }
+        }
+        tx.commit().await?;
 
-        Ok(())
+        for task in tasks {
+            if inserted.contains(&task.id.to_bytes()[..]) {
+                self.events.publish(TaskEventKind::Scheduled, task);
+            }
+        }
+
+        let ids = tasks
+            .iter()
+            .map(|task| {
+                if inserted.contains(&task.id.to_bytes()[..]) {
+                    return Ok(task.id);
+                }
+                debug!(
+                    "Task with idempotency key {:?} already exists",
+                    task.idempotency_key
+                );
+                task.idempotency_key
+                    .as_ref()
+                    .and_then(|key| existing.get(key))
+                    .copied()
+                    .ok_or_else(|| {
+                        anyhow!("Task with a conflicting idempotency key no longer exists")
+                    })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if !inserted.is_empty() {
+            self.notify_changed().await;
+        }
+
+        Ok(ids)
     }
 
     pub async fn get_scheduled_task_count(&self) -> Result<i64> {
@@ -184,18 +1003,23 @@ impl Database {
         Ok(count.count.unwrap_or(0))
     }
 
+    /// Claims up to `batch_size` tasks due by `run_at` for the lifetime of the transaction. Tasks already claimed
+    /// by another instance are skipped.
     async fn get_outstanding_tasks(
         &self,
+        tx: &mut Transaction<'_, Postgres>,
         run_at: Option<DateTime<Utc>>,
     ) -> Result<Vec<DatabaseTask>> {
         let run_at = run_at.unwrap_or_else(Utc::now);
         debug!("Getting oustanding tasks since {:?}", run_at);
         let transport = sqlx::query_as!(
             DatabaseTaskTransport,
-            "SELECT id, exchange, routing_key, run_at, payload FROM tasks WHERE run_at <= $1 ORDER BY run_at ASC",
-            run_at
+            "SELECT id, exchange, routing_key, run_at, payload, cron, timezone, interval_ms, max_runs, until, run_count, misfire_policy, attempts, occurrence_at, content_type, headers, message_id, correlation_id, priority, expiration_ms, delivery_mode, mandatory, idempotency_key, labels, sink, destination FROM tasks WHERE run_at <= $1 AND sink <> ALL($3) ORDER BY run_at ASC LIMIT $2 FOR UPDATE SKIP LOCKED",
+            run_at,
+            self.batch_size,
+            &self.sinks.unavailable()
         )
-        .fetch_all(&self.pool)
+        .fetch_all(&mut **tx)
         .await?;
 
         let tasks = transport
@@ -207,6 +1031,50 @@ impl Database {
         Ok(tasks)
     }
 
+    /// Wakes the scheduler loop of every instance so it re-evaluates when to run next
+    async fn notify_changed(&self) {
+        self.interrupt();
+        if let Err(e) = sqlx::query("SELECT pg_notify($1, '')")
+            .bind(NOTIFY_CHANNEL)
+            .execute(&self.pool)
+            .await
+        {
+            warn!("Failed to notify other instances: {}", e);
+        }
+    }
+
+    /// Interrupts the scheduler loop whenever another instance changes the schedule
+    pub async fn listen(&self) {
+        loop {
+            let mut listener = match PgListener::connect_with(&self.pool).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    error!("Failed to connect schedule listener: {}", e);
+                    sleep(Duration::from_secs(5)).await;
+                    continue;
+                }
+            };
+            if let Err(e) = listener.listen(NOTIFY_CHANNEL).await {
+                error!("Failed to listen for schedule changes: {}", e);
+                sleep(Duration::from_secs(5)).await;
+                continue;
+            }
+            debug!("Listening for schedule changes on {}", NOTIFY_CHANNEL);
+            // Changes may have been missed while the listener was not connected
+            self.interrupt();
+
+            loop {
+                match listener.recv().await {
+                    Ok(_) => self.interrupt(),
+                    Err(e) => {
+                        error!("Schedule listener failed: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
     fn interrupt(&self) {
         if let Some(token) = self.token.lock().unwrap().0.take() {
             debug!("Canceling the previous task");
@@ -231,6 +1099,8 @@ impl Database {
                             }
                             _ = token.cancelled() => {
                             }
+                            _ = self.sinks.wait_any_available() => {
+                            }
                         }
                     } else {
                         warn!("Missed a task by {}ms", -delay);
@@ -239,7 +1109,11 @@ impl Database {
                 }
                 None => {
                     debug!("No tasks found");
-                    self.get_token().cancelled().await;
+                    let token = self.get_token();
+                    select! {
+                        _ = token.cancelled() => {}
+                        _ = self.sinks.wait_any_available() => {}
+                    }
                 }
             }
         }
@@ -252,25 +1126,454 @@ impl TryInto<DatabaseTask> for DatabaseTaskTransport {
         let bytes = self.id.as_slice();
         Ok(DatabaseTask {
             id: Ulid::from_bytes(bytes.try_into()?),
-            routing_key: self.routing_key,
+            sink: self.sink,
+            destination: match self.destination {
+                Some(destination) => serde_json::from_value(destination)?,
+                None => Destination::Amqp(AmqpDestination {
+                    exchange: self.exchange,
+                    routing_key: self.routing_key,
+                    properties: MessageProperties {
+                        content_type: self.content_type,
+                        headers: match self.headers {
+                            Some(headers) => serde_json::from_value(headers)?,
+                            None => HashMap::new(),
+                        },
+                        message_id: self.message_id,
+                        correlation_id: self.correlation_id,
+                        priority: self.priority,
+                        expiration_ms: self.expiration_ms,
+                        delivery_mode: self.delivery_mode,
+                    },
+                    mandatory: self.mandatory,
+                }),
+            },
+            run_at: self.run_at,
+            payload: self.payload,
+            cron: self.cron,
+            timezone: self.timezone,
+            interval: self.interval_ms.map(TimeDelta::milliseconds),
+            max_runs: self.max_runs,
+            until: self.until,
+            run_count: self.run_count,
+            misfire_policy: self.misfire_policy.try_into()?,
+            attempts: self.attempts,
+            occurrence_at: self.occurrence_at,
+            idempotency_key: self.idempotency_key,
+            labels: serde_json::from_value(self.labels)?,
+        })
+    }
+}
+
+impl TryInto<DeadTask> for DeadTaskTransport {
+    type Error = anyhow::Error;
+    fn try_into(self) -> Result<DeadTask> {
+        let task = DatabaseTaskTransport {
+            id: self.id,
             exchange: self.exchange,
+            routing_key: self.routing_key,
             run_at: self.run_at,
             payload: self.payload,
+            cron: self.cron,
+            timezone: self.timezone,
+            interval_ms: self.interval_ms,
+            max_runs: self.max_runs,
+            until: self.until,
+            run_count: self.run_count,
+            misfire_policy: self.misfire_policy,
+            attempts: self.attempts,
+            occurrence_at: None,
+            content_type: self.content_type,
+            headers: self.headers,
+            message_id: self.message_id,
+            correlation_id: self.correlation_id,
+            priority: self.priority,
+            expiration_ms: self.expiration_ms,
+            delivery_mode: self.delivery_mode,
+            mandatory: self.mandatory,
+            idempotency_key: self.idempotency_key,
+            labels: self.labels,
+            sink: self.sink,
+            destination: self.destination,
+        };
+        Ok(DeadTask {
+            task: task.try_into()?,
+            last_error: self.last_error,
+            failed_at: self.failed_at,
+        })
+    }
+}
+
+impl TryInto<TaskHistoryEntry> for TaskHistoryTransport {
+    type Error = anyhow::Error;
+    fn try_into(self) -> Result<TaskHistoryEntry> {
+        Ok(TaskHistoryEntry {
+            id: self.id,
+            task_id: Ulid::from_bytes(self.task_id.as_slice().try_into()?),
+            exchange: self.exchange,
+            routing_key: self.routing_key,
+            scheduled_at: self.scheduled_at,
+            attempted_at: self.attempted_at,
+            attempt: self.attempt,
+            outcome: self.outcome.try_into()?,
+            error: self.error,
+            sink: self.sink,
         })
     }
 }
 
+impl DatabaseTask {
+    /// Returns how this task repeats, or `None` for one-off tasks
+    pub fn recurrence(&self) -> Result<Option<Recurrence>> {
+        if let Some(cron) = self.cron.as_deref() {
+            let schedule = CronSchedule::parse(cron, self.timezone.as_deref())?;
+            return Ok(Some(Recurrence::Cron(Box::new(schedule))));
+        }
+        Ok(self.interval.map(Recurrence::Interval))
+    }
+
+    /// Returns true if this occurrence was missed and the task's misfire policy drops missed
+    /// occurrences. Retries of a failed delivery are never skipped.
+    pub fn should_skip(&self, now: DateTime<Utc>, threshold: TimeDelta) -> bool {
+        self.misfire_policy == MisfirePolicy::Skip
+            && self.attempts == 0
+            && (self.cron.is_some() || self.interval.is_some())
+            && now.signed_duration_since(self.run_at) > threshold
+    }
+
+    /// When the current occurrence was due, which is earlier than `run_at` while it is retried
+    pub fn occurrence(&self) -> DateTime<Utc> {
+        self.occurrence_at.unwrap_or(self.run_at)
+    }
+
+    /// Returns the next time this task should run once the current occurrence has been handled
+    /// and the task has run `run_count` times, or `None` if the task should be retired
+    pub fn next_run_at(&self, now: DateTime<Utc>, run_count: i32) -> Option<DateTime<Utc>> {
+        if self.max_runs.is_some_and(|max_runs| run_count >= max_runs) {
+            return None;
+        }
+        let recurrence = match self.recurrence() {
+            Ok(recurrence) => recurrence?,
+            Err(e) => {
+                error!("Task {} has an invalid recurrence: {}", self.id, e);
+                return None;
+            }
+        };
+        let after = match self.misfire_policy {
+            MisfirePolicy::FireAll => self.occurrence(),
+            MisfirePolicy::FireOnce | MisfirePolicy::Skip => now,
+        };
+        recurrence
+            .next_after(self.occurrence(), after)
+            .filter(|next| match self.until {
+                Some(until) => *next <= until,
+                None => true,
+            })
+    }
+}
+
+#[cfg(test)]
+impl DatabaseTask {
+    /// A one-off task with an empty payload that is due now
+    pub fn for_test(sink: &str, destination: Destination) -> Self {
+        Self {
+            id: Ulid::new(),
+            sink: sink.to_string(),
+            destination,
+            run_at: Utc::now(),
+            payload: Vec::new(),
+            cron: None,
+            timezone: None,
+            interval: None,
+            max_runs: None,
+            until: None,
+            run_count: 0,
+            misfire_policy: MisfirePolicy::default(),
+            attempts: 0,
+            occurrence_at: None,
+            idempotency_key: None,
+            labels: HashMap::new(),
+        }
+    }
+}
+
 impl From<DatabaseTask> for Task {
     fn from(task: DatabaseTask) -> Task {
+        let (amqp, destination) = match task.destination {
+            Destination::Amqp(amqp) => (amqp, None),
+            Destination::Http(http) => (
+                AmqpDestination::default(),
+                Some(rpc::task::Destination::Http(http.into())),
+            ),
+            Destination::Redis(redis) => (
+                AmqpDestination::default(),
+                Some(rpc::task::Destination::Redis(redis.into())),
+            ),
+            Destination::Nats(nats) => (
+                AmqpDestination::default(),
+                Some(rpc::task::Destination::Nats(nats.into())),
+            ),
+            Destination::Kafka(kafka) => (
+                AmqpDestination::default(),
+                Some(rpc::task::Destination::Kafka(kafka.into())),
+            ),
+        };
         Task {
             task_id: task.id.to_bytes().to_vec(),
-            exchange: task.exchange.unwrap_or_default(),
-            routing_key: task.routing_key,
-            run_at: Some(prost_types::Timestamp {
-                seconds: task.run_at.timestamp(),
-                nanos: task.run_at.timestamp_subsec_nanos() as i32,
-            }),
+            exchange: amqp.exchange.unwrap_or_default(),
+            routing_key: amqp.routing_key,
+            run_at: Some(to_timestamp(task.run_at)),
             payload: task.payload,
+            cron: task.cron.unwrap_or_default(),
+            timezone: task.timezone.unwrap_or_default(),
+            interval: task.interval.map(|interval| prost_types::Duration {
+                seconds: interval.num_seconds(),
+                nanos: interval.subsec_nanos(),
+            }),
+            max_runs: task.max_runs.unwrap_or_default() as u32,
+            until: task.until.map(to_timestamp),
+            run_count: task.run_count as u32,
+            misfire_policy: rpc::MisfirePolicy::from(task.misfire_policy).into(),
+            attempts: task.attempts as u32,
+            properties: Some(amqp.properties.into()),
+            mandatory: amqp.mandatory,
+            idempotency_key: task.idempotency_key.unwrap_or_default(),
+            labels: task.labels,
+            sink: task.sink,
+            destination,
         }
     }
 }
+
+impl From<rpc::TaskOrder> for TaskOrder {
+    fn from(order: rpc::TaskOrder) -> TaskOrder {
+        match order {
+            rpc::TaskOrder::RunAtAsc => TaskOrder::RunAtAsc,
+            rpc::TaskOrder::RunAtDesc => TaskOrder::RunAtDesc,
+            rpc::TaskOrder::CreatedAsc => TaskOrder::CreatedAsc,
+            rpc::TaskOrder::CreatedDesc => TaskOrder::CreatedDesc,
+        }
+    }
+}
+
+impl From<DeadTask> for rpc::DeadTask {
+    fn from(dead: DeadTask) -> rpc::DeadTask {
+        rpc::DeadTask {
+            task: Some(dead.task.into()),
+            last_error: dead.last_error,
+            failed_at: Some(to_timestamp(dead.failed_at)),
+        }
+    }
+}
+
+pub fn to_timestamp(time: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, env, sync::Arc, time::Duration};
+
+    use chrono::{DateTime, TimeDelta, Utc};
+    use lapin::{
+        options::{BasicGetOptions, QueueDeclareOptions},
+        types::FieldTable,
+        Connection, ConnectionProperties,
+    };
+    use sqlx::PgPool;
+    use tokio::time::{sleep, timeout};
+
+    use super::{Database, DatabaseTask};
+    use crate::{
+        amqp::{Amqp, AmqpDestination},
+        recurrence::MisfirePolicy,
+        retry::RetryPolicy,
+        sink::{Destination, Sinks, DEFAULT_SINK},
+    };
+
+    const INSTANCES: usize = 3;
+    const TASKS: usize = 50;
+
+    /// Applies the `migrate:up` section of every dbmate migration
+    async fn migrate(pool: &PgPool) {
+        let mut migrations = std::fs::read_dir("db/migrations")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        migrations.sort();
+        for migration in migrations {
+            let sql = std::fs::read_to_string(migration).unwrap();
+            let up = sql.split("-- migrate:down").next().unwrap();
+            sqlx::raw_sql(up).execute(pool).await.unwrap();
+        }
+    }
+
+    fn task(routing_key: &str) -> DatabaseTask {
+        let destination = Destination::Amqp(AmqpDestination {
+            routing_key: routing_key.to_string(),
+            ..Default::default()
+        });
+        DatabaseTask {
+            run_at: Utc::now() + TimeDelta::seconds(1),
+            ..DatabaseTask::for_test(DEFAULT_SINK, destination)
+        }
+    }
+
+    #[sqlx::test(migrations = false)]
+    #[ignore = "requires DATABASE_URL and AMQP_ADDR"]
+    async fn concurrent_instances_deliver_each_task_once(pool: PgPool) {
+        migrate(&pool).await;
+        let addr = env::var("AMQP_ADDR").unwrap();
+
+        let connection = Connection::connect(&addr, ConnectionProperties::default())
+            .await
+            .unwrap();
+        let channel = connection.create_channel().await.unwrap();
+        let queue = channel
+            .queue_declare(
+                "",
+                QueueDeclareOptions {
+                    exclusive: true,
+                    ..Default::default()
+                },
+                FieldTable::default(),
+            )
+            .await
+            .unwrap();
+        let queue = queue.name().as_str();
+
+        let mut instances = Vec::new();
+        for _ in 0..INSTANCES {
+            let mut sinks = Sinks::new();
+            sinks
+                .register(
+                    DEFAULT_SINK,
+                    Arc::new(Amqp::new(DEFAULT_SINK, &addr).await.unwrap()),
+                )
+                .unwrap();
+            let retry_policy = RetryPolicy {
+                max_attempts: 1,
+                base_delay: TimeDelta::zero(),
+                max_delay: TimeDelta::zero(),
+            };
+            instances.push(Arc::new(Database::new(
+                pool.clone(),
+                Arc::new(sinks),
+                retry_policy,
+                10,
+                TimeDelta::minutes(1),
+            )));
+        }
+
+        let mut scheduled = HashSet::new();
+        for _ in 0..TASKS {
+            let task = task(queue);
+            instances[0].schedule(&task).await.unwrap();
+            scheduled.insert(task.id.to_string());
+        }
+
+        let handles = instances
+            .iter()
+            .map(|db| {
+                let db = db.clone();
+                tokio::spawn(async move { db.run().await })
+            })
+            .collect::<Vec<_>>();
+
+        timeout(Duration::from_secs(30), async {
+            while instances[0].get_scheduled_task_count().await.unwrap() > 0 {
+                sleep(Duration::from_millis(100)).await;
+            }
+        })
+        .await
+        .expect("tasks were not delivered in time");
+        handles.iter().for_each(|handle| handle.abort());
+
+        let mut delivered = Vec::new();
+        while let Some(message) = channel
+            .basic_get(queue, BasicGetOptions { no_ack: true })
+            .await
+            .unwrap()
+        {
+            let message_id = message.delivery.properties.message_id().clone().unwrap();
+            delivered.push(message_id.to_string());
+        }
+
+        assert_eq!(delivered.len(), TASKS);
+        assert_eq!(delivered.into_iter().collect::<HashSet<_>>(), scheduled);
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    fn every_minute(misfire_policy: MisfirePolicy) -> DatabaseTask {
+        DatabaseTask {
+            run_at: at(0),
+            interval: Some(TimeDelta::minutes(1)),
+            misfire_policy,
+            ..task("misfire")
+        }
+    }
+
+    #[test]
+    fn fire_once_after_outage() {
+        let task = every_minute(MisfirePolicy::FireOnce);
+        assert!(!task.should_skip(at(630), TimeDelta::minutes(1)));
+        assert_eq!(task.next_run_at(at(630), 1), Some(at(660)));
+    }
+
+    #[test]
+    fn fire_all_after_outage() {
+        let task = every_minute(MisfirePolicy::FireAll);
+        assert!(!task.should_skip(at(630), TimeDelta::minutes(1)));
+        assert_eq!(task.next_run_at(at(630), 1), Some(at(60)));
+    }
+
+    #[test]
+    fn skip_after_outage() {
+        let task = every_minute(MisfirePolicy::Skip);
+        assert!(task.should_skip(at(630), TimeDelta::minutes(1)));
+        assert!(!task.should_skip(at(30), TimeDelta::minutes(1)));
+        assert_eq!(task.next_run_at(at(630), 0), Some(at(660)));
+
+        let retry = DatabaseTask {
+            attempts: 1,
+            ..every_minute(MisfirePolicy::Skip)
+        };
+        assert!(!retry.should_skip(at(630), TimeDelta::minutes(1)));
+    }
+
+    #[test]
+    fn retry_keeps_series_aligned() {
+        let task = DatabaseTask {
+            run_at: at(15),
+            occurrence_at: Some(at(0)),
+            attempts: 1,
+            ..every_minute(MisfirePolicy::FireAll)
+        };
+        assert_eq!(task.next_run_at(at(15), 1), Some(at(60)));
+    }
+
+    #[test]
+    fn max_runs_ends_task() {
+        let task = DatabaseTask {
+            max_runs: Some(3),
+            ..every_minute(MisfirePolicy::FireOnce)
+        };
+        assert_eq!(task.next_run_at(at(0), 2), Some(at(60)));
+        assert_eq!(task.next_run_at(at(0), 3), None);
+    }
+
+    #[test]
+    fn until_ends_task() {
+        let task = DatabaseTask {
+            until: Some(at(90)),
+            ..every_minute(MisfirePolicy::FireOnce)
+        };
+        assert_eq!(task.next_run_at(at(0), 1), Some(at(60)));
+        assert_eq!(task.next_run_at(at(60), 2), None);
+    }
+}
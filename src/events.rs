@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use tokio::sync::broadcast;
+
+use crate::{
+    db::{to_timestamp, DatabaseTask},
+    protos::rpc,
+};
+
+/// Events buffered per subscriber before a slow subscriber starts missing events
+const EVENT_BUFFER: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskEventKind {
+    Scheduled,
+    Updated,
+    Cancelled,
+    Delivered,
+    /// Delivery failed and will be retried
+    Failed,
+    DeadLettered,
+}
+
+#[derive(Clone)]
+pub struct TaskEvent {
+    pub kind: TaskEventKind,
+    pub task: DatabaseTask,
+    pub time: DateTime<Utc>,
+}
+
+/// Fans task lifecycle events out to everyone watching this instance
+pub struct EventBus {
+    sender: broadcast::Sender<TaskEvent>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::Sender::new(EVENT_BUFFER),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<TaskEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Publishes an event, skipping the copy of the task when nobody is watching
+    pub fn publish(&self, kind: TaskEventKind, task: &DatabaseTask) {
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+        let _ = self.sender.send(TaskEvent {
+            kind,
+            task: task.clone(),
+            time: Utc::now(),
+        });
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<TaskEventKind> for rpc::TaskEventType {
+    fn from(kind: TaskEventKind) -> rpc::TaskEventType {
+        match kind {
+            TaskEventKind::Scheduled => rpc::TaskEventType::Scheduled,
+            TaskEventKind::Updated => rpc::TaskEventType::Updated,
+            TaskEventKind::Cancelled => rpc::TaskEventType::Cancelled,
+            TaskEventKind::Delivered => rpc::TaskEventType::Delivered,
+            TaskEventKind::Failed => rpc::TaskEventType::Failed,
+            TaskEventKind::DeadLettered => rpc::TaskEventType::DeadLettered,
+        }
+    }
+}
+
+impl From<TaskEvent> for rpc::TaskEvent {
+    fn from(event: TaskEvent) -> rpc::TaskEvent {
+        rpc::TaskEvent {
+            r#type: rpc::TaskEventType::from(event.kind).into(),
+            task: Some(event.task.into()),
+            time: Some(to_timestamp(event.time)),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use ulid::Ulid;
+
+use crate::{db::to_timestamp, protos::rpc};
+
+/// What happened when a task came due
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    Delivered,
+    /// Publishing failed and the task will be retried
+    Failed,
+    /// Publishing failed and the task ran out of attempts
+    DeadLettered,
+    /// The occurrence was missed and dropped by the task's misfire policy
+    Skipped,
+}
+
+/// A single recorded delivery attempt
+pub struct TaskHistoryEntry {
+    pub id: i64,
+    pub task_id: Ulid,
+    pub sink: String,
+    pub exchange: Option<String>,
+    pub routing_key: String,
+    /// When the task was due
+    pub scheduled_at: DateTime<Utc>,
+    pub attempted_at: DateTime<Utc>,
+    /// Which delivery attempt of the occurrence this was, starting at 1
+    pub attempt: i32,
+    pub outcome: DeliveryOutcome,
+    pub error: Option<String>,
+}
+
+impl From<DeliveryOutcome> for i16 {
+    fn from(outcome: DeliveryOutcome) -> i16 {
+        match outcome {
+            DeliveryOutcome::Delivered => 0,
+            DeliveryOutcome::Failed => 1,
+            DeliveryOutcome::DeadLettered => 2,
+            DeliveryOutcome::Skipped => 3,
+        }
+    }
+}
+
+impl TryFrom<i16> for DeliveryOutcome {
+    type Error = anyhow::Error;
+    fn try_from(value: i16) -> Result<Self> {
+        match value {
+            0 => Ok(DeliveryOutcome::Delivered),
+            1 => Ok(DeliveryOutcome::Failed),
+            2 => Ok(DeliveryOutcome::DeadLettered),
+            3 => Ok(DeliveryOutcome::Skipped),
+            _ => Err(anyhow!("Unknown delivery outcome: {}", value)),
+        }
+    }
+}
+
+impl From<DeliveryOutcome> for rpc::DeliveryOutcome {
+    fn from(outcome: DeliveryOutcome) -> rpc::DeliveryOutcome {
+        match outcome {
+            DeliveryOutcome::Delivered => rpc::DeliveryOutcome::Delivered,
+            DeliveryOutcome::Failed => rpc::DeliveryOutcome::Failed,
+            DeliveryOutcome::DeadLettered => rpc::DeliveryOutcome::DeadLettered,
+            DeliveryOutcome::Skipped => rpc::DeliveryOutcome::Skipped,
+        }
+    }
+}
+
+impl From<TaskHistoryEntry> for rpc::TaskHistoryEntry {
+    fn from(entry: TaskHistoryEntry) -> rpc::TaskHistoryEntry {
+        rpc::TaskHistoryEntry {
+            id: entry.id as u64,
+            task_id: entry.task_id.to_bytes().to_vec(),
+            sink: entry.sink,
+            exchange: entry.exchange.unwrap_or_default(),
+            routing_key: entry.routing_key,
+            scheduled_at: Some(to_timestamp(entry.scheduled_at)),
+            attempted_at: Some(to_timestamp(entry.attempted_at)),
+            attempt: entry.attempt as u32,
+            outcome: rpc::DeliveryOutcome::from(entry.outcome).into(),
+            error: entry.error.unwrap_or_default(),
+        }
+    }
+}
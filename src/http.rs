@@ -0,0 +1,233 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::{anyhow, ensure, Result};
+use async_trait::async_trait;
+use reqwest::{
+    header::{HeaderName, HeaderValue},
+    Client, Method, Url,
+};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    db::DatabaseTask,
+    protos::rpc,
+    sink::{Destination, Sink},
+};
+
+/// Registered when `HTTP_SINK_ENABLED` is true, timing requests out after `HTTP_TIMEOUT_MS` unless
+/// the task overrides it
+pub const HTTP_SINK: &str = "http";
+
+/// Sends a task's payload as the body of an HTTP request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpDestination {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Overrides the sink's default timeout
+    pub timeout_ms: Option<u64>,
+}
+
+pub struct Http {
+    client: Client,
+    timeout: Duration,
+}
+
+impl Http {
+    pub fn new(timeout: Duration) -> Result<Self> {
+        Ok(Self {
+            client: Client::builder().build()?,
+            timeout,
+        })
+    }
+}
+
+#[async_trait]
+impl Sink for Http {
+    async fn deliver(&self, task: &DatabaseTask) -> Result<()> {
+        let Destination::Http(destination) = &task.destination else {
+            return Err(anyhow!("Task {} is not an HTTP task", task.id));
+        };
+        debug!(
+            "Sending {} request to {}",
+            destination.method, destination.url
+        );
+
+        let timeout = destination
+            .timeout_ms
+            .map(Duration::from_millis)
+            .unwrap_or(self.timeout);
+        let mut request = self
+            .client
+            .request(
+                Method::from_bytes(destination.method.as_bytes())?,
+                &destination.url,
+            )
+            .timeout(timeout)
+            .header("X-Task-Id", task.id.to_string())
+            .body(task.payload.clone());
+        for (name, value) in &destination.headers {
+            request = request.header(name, value);
+        }
+
+        let status = request.send().await?.status();
+        ensure!(
+            status.is_success(),
+            "Request to {} failed with status {}",
+            destination.url,
+            status
+        );
+        Ok(())
+    }
+
+    fn accepts(&self, destination: &Destination) -> bool {
+        matches!(destination, Destination::Http(_))
+    }
+}
+
+impl TryFrom<&rpc::HttpDestination> for HttpDestination {
+    type Error = anyhow::Error;
+    fn try_from(destination: &rpc::HttpDestination) -> Result<Self> {
+        let method = match destination.method.as_str() {
+            "" => "POST".to_string(),
+            method => method.to_uppercase(),
+        };
+        Method::from_bytes(method.as_bytes()).map_err(|_| anyhow!("invalid method {}", method))?;
+        let url = Url::parse(&destination.url).map_err(|e| anyhow!("invalid url: {}", e))?;
+        ensure!(
+            matches!(url.scheme(), "http" | "https"),
+            "url must be http or https"
+        );
+        for (name, value) in &destination.headers {
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| anyhow!("invalid http header name {:?}", name))?;
+            HeaderValue::from_str(value)
+                .map_err(|_| anyhow!("invalid value for http header {}", name))?;
+        }
+        let timeout_ms = match destination.timeout {
+            Some(timeout) => {
+                ensure!(
+                    timeout.seconds >= 0 && timeout.nanos >= 0,
+                    "timeout must be positive"
+                );
+                let timeout_ms = (timeout.seconds as u64)
+                    .checked_mul(1000)
+                    .and_then(|ms| ms.checked_add(timeout.nanos as u64 / 1_000_000))
+                    .ok_or_else(|| anyhow!("timeout is too large"))?;
+                Some(timeout_ms).filter(|timeout_ms| *timeout_ms > 0)
+            }
+            None => None,
+        };
+
+        Ok(HttpDestination {
+            method,
+            url: destination.url.clone(),
+            headers: destination.headers.clone(),
+            timeout_ms,
+        })
+    }
+}
+
+impl From<HttpDestination> for rpc::HttpDestination {
+    fn from(destination: HttpDestination) -> rpc::HttpDestination {
+        rpc::HttpDestination {
+            method: destination.method,
+            url: destination.url,
+            headers: destination.headers,
+            timeout: destination
+                .timeout_ms
+                .map(|timeout_ms| prost_types::Duration {
+                    seconds: (timeout_ms / 1000) as i64,
+                    nanos: (timeout_ms % 1000) as i32 * 1_000_000,
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, net::SocketAddr, time::Duration};
+
+    use tokio::sync::mpsc;
+    use warp::{http::StatusCode, hyper::body::Bytes, Filter};
+
+    use super::{Http, HttpDestination, HTTP_SINK};
+    use crate::{db::DatabaseTask, protos::rpc, sink::Destination, sink::Sink};
+
+    /// Starts a stub server that replies with `status` and forwards what it received
+    fn stub(status: StatusCode) -> (SocketAddr, mpsc::UnboundedReceiver<(String, Bytes)>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let route = warp::post()
+            .and(warp::header::<String>("x-task-id"))
+            .and(warp::body::bytes())
+            .map(move |task_id: String, body: Bytes| {
+                sender.send((task_id, body)).unwrap();
+                warp::reply::with_status("", status)
+            });
+        let (addr, server) = warp::serve(route).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+        (addr, receiver)
+    }
+
+    fn task(addr: SocketAddr) -> DatabaseTask {
+        let destination = Destination::Http(HttpDestination {
+            method: "POST".to_string(),
+            url: format!("http://{}/hook", addr),
+            headers: HashMap::new(),
+            timeout_ms: None,
+        });
+        DatabaseTask {
+            payload: b"hello".to_vec(),
+            ..DatabaseTask::for_test(HTTP_SINK, destination)
+        }
+    }
+
+    #[tokio::test]
+    async fn delivers_payload() {
+        let (addr, mut requests) = stub(StatusCode::OK);
+        let http = Http::new(Duration::from_secs(5)).unwrap();
+        let task = task(addr);
+
+        http.deliver(&task).await.unwrap();
+
+        let (task_id, body) = requests.recv().await.unwrap();
+        assert_eq!(task_id, task.id.to_string());
+        assert_eq!(&body[..], b"hello");
+    }
+
+    #[tokio::test]
+    async fn non_success_status_fails() {
+        let (addr, _requests) = stub(StatusCode::SERVICE_UNAVAILABLE);
+        let http = Http::new(Duration::from_secs(5)).unwrap();
+
+        assert!(http.deliver(&task(addr)).await.is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        let destination = |name: &str, value: &str| rpc::HttpDestination {
+            url: "http://localhost/hook".to_string(),
+            headers: [(name.to_string(), value.to_string())].into(),
+            ..Default::default()
+        };
+        assert!(HttpDestination::try_from(&destination("X-Test", "value")).is_ok());
+        assert!(HttpDestination::try_from(&destination("X Test", "value")).is_err());
+        assert!(HttpDestination::try_from(&destination("", "value")).is_err());
+        assert!(HttpDestination::try_from(&destination("X-Test", "a\r\nb")).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_timeout() {
+        let destination = rpc::HttpDestination {
+            url: "http://localhost/hook".to_string(),
+            timeout: Some(prost_types::Duration {
+                seconds: i64::MAX,
+                nanos: 0,
+            }),
+            ..Default::default()
+        };
+        assert!(HttpDestination::try_from(&destination).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::{anyhow, ensure, Result};
+use async_trait::async_trait;
+use rdkafka::{
+    message::{Header, OwnedHeaders},
+    producer::{FutureProducer, FutureRecord},
+    ClientConfig,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::{
+    db::DatabaseTask,
+    protos::rpc,
+    sink::{Destination, Sink},
+};
+
+/// Registered when `KAFKA_BROKERS` is set
+pub const KAFKA_SINK: &str = "kafka";
+
+/// How long to wait for room in the producer's queue before failing a delivery
+const QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Produces a task's payload to a Kafka topic
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KafkaDestination {
+    pub topic: String,
+    /// Records with the same key are written to the same partition
+    pub key: Option<String>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+pub struct Kafka {
+    producer: FutureProducer,
+}
+
+impl Kafka {
+    /// Creates a producer that gives up on a record once it hasn't been acknowledged within
+    /// `timeout`, so failed deliveries are retried instead of holding up the batch
+    pub fn new(brokers: &str, timeout: Duration) -> Result<Self> {
+        info!("Connecting to Kafka brokers at {}", brokers);
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("acks", "all")
+            .set("message.timeout.ms", timeout.as_millis().to_string())
+            .set("enable.idempotence", "true")
+            .create()?;
+        Ok(Self { producer })
+    }
+}
+
+#[async_trait]
+impl Sink for Kafka {
+    async fn deliver(&self, task: &DatabaseTask) -> Result<()> {
+        let Destination::Kafka(destination) = &task.destination else {
+            return Err(anyhow!("Task {} is not a Kafka task", task.id));
+        };
+        debug!("Producing record to Kafka topic {}", destination.topic);
+
+        let mut headers = OwnedHeaders::new();
+        for (key, value) in &destination.headers {
+            headers = headers.insert(Header {
+                key,
+                value: Some(value),
+            });
+        }
+        let mut record = FutureRecord::to(&destination.topic)
+            .payload(&task.payload)
+            .headers(headers);
+        if let Some(key) = &destination.key {
+            record = record.key(key);
+        }
+
+        let (partition, offset) = self
+            .producer
+            .send(record, QUEUE_TIMEOUT)
+            .await
+            .map_err(|(err, _)| err)?;
+        debug!(
+            "Produced record to partition {} at offset {}",
+            partition, offset
+        );
+        Ok(())
+    }
+
+    fn accepts(&self, destination: &Destination) -> bool {
+        matches!(destination, Destination::Kafka(_))
+    }
+}
+
+impl TryFrom<&rpc::KafkaDestination> for KafkaDestination {
+    type Error = anyhow::Error;
+    fn try_from(destination: &rpc::KafkaDestination) -> Result<Self> {
+        ensure!(!destination.topic.is_empty(), "kafka topic is required");
+        Ok(KafkaDestination {
+            topic: destination.topic.clone(),
+            key: Some(destination.key.clone()).filter(|key| !key.is_empty()),
+            headers: destination.headers.clone(),
+        })
+    }
+}
+
+impl From<KafkaDestination> for rpc::KafkaDestination {
+    fn from(destination: KafkaDestination) -> rpc::KafkaDestination {
+        rpc::KafkaDestination {
+            topic: destination.topic,
+            key: destination.key.unwrap_or_default(),
+            headers: destination.headers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, time::Duration};
+
+    use rdkafka::{
+        consumer::{Consumer, StreamConsumer},
+        message::Headers,
+        ClientConfig, Message,
+    };
+    use tokio::time::timeout;
+    use ulid::Ulid;
+
+    use super::{Kafka, KafkaDestination, KAFKA_SINK};
+    use crate::{db::DatabaseTask, sink::Destination, sink::Sink};
+
+    #[tokio::test]
+    #[ignore = "requires KAFKA_BROKERS"]
+    async fn produces_keyed_record() {
+        let brokers = env::var("KAFKA_BROKERS").unwrap();
+        let topic = format!("task-scheduler-test-{}", Ulid::new());
+        let task = DatabaseTask {
+            payload: b"hello".to_vec(),
+            ..DatabaseTask::for_test(
+                KAFKA_SINK,
+                Destination::Kafka(KafkaDestination {
+                    topic: topic.clone(),
+                    key: Some("key".to_string()),
+                    headers: [("test".to_string(), "value".to_string())].into(),
+                }),
+            )
+        };
+        Kafka::new(&brokers, Duration::from_secs(30))
+            .unwrap()
+            .deliver(&task)
+            .await
+            .unwrap();
+
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", &brokers)
+            .set("group.id", topic.as_str())
+            .set("auto.offset.reset", "earliest")
+            .create()
+            .unwrap();
+        consumer.subscribe(&[topic.as_str()]).unwrap();
+        let message = timeout(Duration::from_secs(30), consumer.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.payload(), Some(&b"hello"[..]));
+        assert_eq!(message.key(), Some(&b"key"[..]));
+        let header = message.headers().unwrap().get(0);
+        assert_eq!(header.key, "test");
+        assert_eq!(header.value, Some(&b"value"[..]));
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use sqlx::{PgConnection, PgPool};
+use tokio::{select, time::sleep};
+use tracing::{debug, error, info, warn};
+
+use crate::{db::Database, prometheus::metrics::SCHEDULER_IS_LEADER};
+
+/// Advisory lock held by the instance running the scheduler loop
+const LEADER_LOCK_ID: i64 = 0x7461736b73;
+
+const CAMPAIGN_INTERVAL: Duration = Duration::from_secs(5);
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Runs the scheduler loop only while this instance holds the leader lock. The lock is tied to
+/// a dedicated session, so it is released as soon as the leader's connection drops and another
+/// instance takes over.
+pub async fn run(pool: PgPool, db: &Database) {
+    loop {
+        let mut connection = match pool.acquire().await {
+            Ok(connection) => connection.detach(),
+            Err(e) => {
+                error!("Failed to acquire connection for leader election: {}", e);
+                sleep(CAMPAIGN_INTERVAL).await;
+                continue;
+            }
+        };
+
+        if !campaign(&mut connection).await {
+            continue;
+        }
+
+        info!("Acquired leader lock, starting scheduler");
+        SCHEDULER_IS_LEADER.set(1);
+        select! {
+            _ = db.run() => {}
+            _ = watch_session(&mut connection) => {
+                warn!("Lost leader session, stopping scheduler");
+            }
+        }
+        SCHEDULER_IS_LEADER.set(0);
+    }
+}
+
+/// Waits until the leader lock is acquired. Returns false if the session was lost.
+async fn campaign(connection: &mut PgConnection) -> bool {
+    loop {
+        let acquired = sqlx::query_scalar::<_, bool>("SELECT pg_try_advisory_lock($1)")
+            .bind(LEADER_LOCK_ID)
+            .fetch_one(&mut *connection)
+            .await;
+        match acquired {
+            Ok(true) => return true,
+            Ok(false) => {
+                debug!("Another instance is the leader");
+                sleep(CAMPAIGN_INTERVAL).await;
+            }
+            Err(e) => {
+                error!("Failed to acquire leader lock: {}", e);
+                return false;
+            }
+        }
+    }
+}
+
+/// Returns once the session holding the leader lock is no longer usable
+async fn watch_session(connection: &mut PgConnection) {
+    loop {
+        sleep(HEALTH_CHECK_INTERVAL).await;
+        if let Err(e) = sqlx::query("SELECT 1").execute(&mut *connection).await {
+            error!("Leader session failed: {}", e);
+            return;
+        }
+    }
+}
@@ -1,23 +1,46 @@
+#![allow(clippy::result_large_err)]
+
 use amqp::Amqp;
-use anyhow::Result;
+use anyhow::{anyhow, ensure, Result};
+use chrono::{TimeDelta, Utc};
 use db::Database;
 use dotenvy::dotenv;
-
-use prometheus::{metrics::TOTAL_TASKS, serve};
+use futures::future::join_all;
+use http::{Http, HTTP_SINK};
+
+use kafka::{Kafka, KAFKA_SINK};
+use nats::{Nats, NATS_SINK};
+use prometheus::{
+    metrics::{SCHEDULER_IS_LEADER, TOTAL_TASKS},
+    serve,
+};
 use protos::rpc::task_scheduler_server::TaskSchedulerServer;
+use redis::{Redis, REDIS_SINK};
+use retry::RetryPolicy;
 use rpc_server::RpcServer;
+use sink::{Sinks, DEFAULT_SINK};
 use sqlx::postgres::PgPoolOptions;
 use std::{env, sync::Arc, time::Duration};
 use tokio::time::sleep;
 use tonic::transport::Server;
-use tracing::{debug, info, warn};
+use tracing::{debug, error, info, warn};
 
 mod amqp;
 mod db;
+mod events;
+mod history;
+mod http;
 mod id;
+mod kafka;
+mod leader;
+mod nats;
 mod prometheus;
 mod protos;
+mod recurrence;
+mod redis;
+mod retry;
 mod rpc_server;
+mod sink;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -30,7 +53,66 @@ async fn main() -> Result<()> {
 
     let addr = env::var("AMQP_ADDR")?;
 
-    let amqp = Arc::new(Amqp::new(&addr).await?);
+    let amqp = Arc::new(Amqp::new(DEFAULT_SINK, &addr).await?);
+    let mut sinks = Sinks::new();
+    sinks.register(DEFAULT_SINK, amqp.clone())?;
+
+    // Off by default, as it lets any client make the scheduler send requests on its behalf
+    let http_sink_enabled = env::var("HTTP_SINK_ENABLED")
+        .unwrap_or("false".to_string())
+        .parse::<bool>()?;
+    if http_sink_enabled {
+        let http_timeout = env::var("HTTP_TIMEOUT_MS")
+            .unwrap_or("30000".to_string())
+            .parse::<u64>()?;
+        ensure!(http_timeout > 0, "HTTP_TIMEOUT_MS must be positive");
+        sinks.register(
+            HTTP_SINK,
+            Arc::new(Http::new(Duration::from_millis(http_timeout))?),
+        )?;
+    }
+
+    if let Ok(redis_url) = env::var("REDIS_URL") {
+        let redis_timeout = env::var("REDIS_TIMEOUT_MS")
+            .unwrap_or("30000".to_string())
+            .parse::<u64>()?;
+        ensure!(redis_timeout > 0, "REDIS_TIMEOUT_MS must be positive");
+        sinks.register(
+            REDIS_SINK,
+            Arc::new(Redis::new(&redis_url, Duration::from_millis(redis_timeout)).await?),
+        )?;
+    }
+
+    if let Ok(nats_url) = env::var("NATS_URL") {
+        sinks.register(NATS_SINK, Arc::new(Nats::new(&nats_url).await?))?;
+    }
+
+    if let Ok(kafka_brokers) = env::var("KAFKA_BROKERS") {
+        let kafka_timeout = env::var("KAFKA_TIMEOUT_MS")
+            .unwrap_or("30000".to_string())
+            .parse::<u64>()?;
+        ensure!(kafka_timeout > 0, "KAFKA_TIMEOUT_MS must be positive");
+        sinks.register(
+            KAFKA_SINK,
+            Arc::new(Kafka::new(
+                &kafka_brokers,
+                Duration::from_millis(kafka_timeout),
+            )?),
+        )?;
+    }
+
+    // Additional brokers as comma separated name=address pairs
+    let mut brokers = vec![amqp];
+    if let Ok(amqp_sinks) = env::var("AMQP_SINKS") {
+        for sink in amqp_sinks.split(',').filter(|sink| !sink.is_empty()) {
+            let (name, addr) = sink
+                .split_once('=')
+                .ok_or_else(|| anyhow!("AMQP_SINKS entries must be name=address"))?;
+            let broker = Arc::new(Amqp::new(name, addr).await?);
+            sinks.register(name, broker.clone())?;
+            brokers.push(broker);
+        }
+    }
 
     let db_url = env::var("DATABASE_URL")?;
     debug!("Connecting to database: {}", db_url);
@@ -44,7 +126,40 @@ async fn main() -> Result<()> {
         .connect(&db_url)
         .await?;
 
-    let db = Arc::new(Database::new(pool, amqp.clone()));
+    let retry_policy = RetryPolicy::from_env()?;
+
+    let batch_size = env::var("DELIVERY_BATCH_SIZE")
+        .unwrap_or("100".to_string())
+        .parse::<i64>()?;
+    ensure!(batch_size > 0, "DELIVERY_BATCH_SIZE must be positive");
+
+    let misfire_threshold = env::var("MISFIRE_THRESHOLD_MS")
+        .unwrap_or("60000".to_string())
+        .parse::<i64>()?;
+    ensure!(
+        misfire_threshold >= 0,
+        "MISFIRE_THRESHOLD_MS must not be negative"
+    );
+
+    let history_retention = env::var("TASK_HISTORY_RETENTION_DAYS")
+        .unwrap_or("30".to_string())
+        .parse::<i64>()?;
+    ensure!(
+        history_retention > 0,
+        "TASK_HISTORY_RETENTION_DAYS must be positive"
+    );
+
+    let leader_election = env::var("LEADER_ELECTION")
+        .unwrap_or("false".to_string())
+        .parse::<bool>()?;
+
+    let db = Arc::new(Database::new(
+        pool.clone(),
+        Arc::new(sinks),
+        retry_policy,
+        batch_size,
+        TimeDelta::milliseconds(misfire_threshold),
+    ));
 
     let server_address = env::var("GRPC_SERVER_ADDRESS").unwrap_or("[::1]:50051".to_string());
 
@@ -56,15 +171,28 @@ async fn main() -> Result<()> {
         .serve(server_address.parse()?);
 
     let statistics = collect_statistics(db.clone());
+    let history = prune_task_history(db.clone(), TimeDelta::days(history_retention));
+    let listener = db.clone();
 
     info!("Ready!");
     let _ = tokio::join!(
         serve(),
+        join_all(brokers.iter().map(|broker| broker.run())),
+        async move {
+            listener.listen().await;
+        },
         async move {
-            db.run().await;
+            if leader_election {
+                info!("Leader election enabled");
+                leader::run(pool, &db).await;
+            } else {
+                SCHEDULER_IS_LEADER.set(1);
+                db.run().await;
+            }
         },
         server,
-        statistics
+        statistics,
+        history
     );
 
     Ok(())
@@ -79,3 +207,16 @@ async fn collect_statistics(db: Arc<Database>) {
         sleep(Duration::from_secs(30)).await;
     }
 }
+
+/// Deletes task history older than `retention` once an hour
+async fn prune_task_history(db: Arc<Database>, retention: TimeDelta) {
+    loop {
+        match db.purge_task_history_before(Utc::now() - retention).await {
+            Ok(0) => {}
+            Ok(purged) => info!("Pruned {} task history entries", purged),
+            Err(e) => error!("Failed to prune task history: {}", e),
+        }
+
+        sleep(Duration::from_secs(3600)).await;
+    }
+}
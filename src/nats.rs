@@ -0,0 +1,260 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, ensure, Result};
+use async_nats::{header::NATS_MESSAGE_ID, jetstream, Client, ConnectOptions, Event, HeaderMap};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+use crate::{
+    amqp::MessageProperties,
+    db::DatabaseTask,
+    protos::rpc,
+    sink::{Destination, Sink},
+};
+
+/// Registered when `NATS_URL` is set
+pub const NATS_SINK: &str = "nats";
+
+/// Publishes a task's payload to a NATS subject
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NatsDestination {
+    pub subject: String,
+    /// Publish through JetStream and wait for the stream's ack
+    pub jetstream: bool,
+    pub properties: MessageProperties,
+}
+
+pub struct Nats {
+    client: Client,
+    jetstream: jetstream::Context,
+    connected: Arc<watch::Sender<bool>>,
+}
+
+impl Nats {
+    pub async fn new(url: &str) -> Result<Self> {
+        info!("Connecting to NATS server at {}", url);
+        let connected = Arc::new(watch::Sender::new(true));
+        let events = connected.clone();
+        let client = ConnectOptions::new()
+            .event_callback(move |event| {
+                let events = events.clone();
+                async move {
+                    match event {
+                        Event::Connected => {
+                            info!("Reconnected to NATS server");
+                            events.send_replace(true);
+                        }
+                        Event::Disconnected => {
+                            warn!("Lost connection to NATS server");
+                            events.send_replace(false);
+                        }
+                        event => debug!("NATS event: {}", event),
+                    }
+                }
+            })
+            .connect(url)
+            .await?;
+        Ok(Self {
+            jetstream: jetstream::new(client.clone()),
+            client,
+            connected,
+        })
+    }
+}
+
+#[async_trait]
+impl Sink for Nats {
+    async fn deliver(&self, task: &DatabaseTask) -> Result<()> {
+        let Destination::Nats(destination) = &task.destination else {
+            return Err(anyhow!("Task {} is not a NATS task", task.id));
+        };
+        debug!(
+            "Publishing message to NATS subject {} (jetstream: {})",
+            destination.subject, destination.jetstream
+        );
+
+        let headers = headers(task, &destination.properties);
+        let payload = task.payload.clone().into();
+        if destination.jetstream {
+            self.jetstream
+                .publish_with_headers(destination.subject.clone(), headers, payload)
+                .await?
+                .await?;
+        } else {
+            self.client
+                .publish_with_headers(destination.subject.clone(), headers, payload)
+                .await?;
+            self.client.flush().await?;
+        }
+        Ok(())
+    }
+
+    fn accepts(&self, destination: &Destination) -> bool {
+        matches!(destination, Destination::Nats(_))
+    }
+
+    fn is_available(&self) -> bool {
+        *self.connected.borrow()
+    }
+
+    async fn wait_available(&self) {
+        let _ = self
+            .connected
+            .subscribe()
+            .wait_for(|connected| *connected)
+            .await;
+    }
+}
+
+/// Maps a task's properties to NATS headers, using the task ID as the message ID unless one was
+/// given
+fn headers(task: &DatabaseTask, properties: &MessageProperties) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (key, value) in &properties.headers {
+        headers.insert(key.as_str(), value.as_str());
+    }
+    if let Some(content_type) = &properties.content_type {
+        headers.insert("Content-Type", content_type.as_str());
+    }
+    if let Some(correlation_id) = &properties.correlation_id {
+        headers.insert("Correlation-Id", correlation_id.as_str());
+    }
+    let message_id = properties
+        .message_id
+        .clone()
+        .unwrap_or_else(|| task.id.to_string());
+    headers.insert(NATS_MESSAGE_ID, message_id.as_str());
+    headers
+}
+
+impl TryFrom<&rpc::NatsDestination> for NatsDestination {
+    type Error = anyhow::Error;
+    fn try_from(destination: &rpc::NatsDestination) -> Result<Self> {
+        ensure!(!destination.subject.is_empty(), "nats subject is required");
+        ensure!(
+            !destination.subject.contains(char::is_whitespace),
+            "nats subject must not contain whitespace"
+        );
+        let properties = destination.properties.clone().unwrap_or_default();
+        ensure!(
+            properties.priority == 0
+                && properties.expiration_ms == 0
+                && properties.delivery_mode == rpc::DeliveryMode::Unspecified as i32,
+            "priority, expiration_ms and delivery_mode are not supported by nats"
+        );
+        for (name, value) in &properties.headers {
+            ensure!(
+                !name.is_empty() && name.bytes().all(|b| b.is_ascii_graphic() && b != b':'),
+                "invalid nats header name {:?}",
+                name
+            );
+            ensure_header_value(value)?;
+        }
+        for value in [
+            &properties.content_type,
+            &properties.message_id,
+            &properties.correlation_id,
+        ] {
+            ensure_header_value(value)?;
+        }
+
+        Ok(NatsDestination {
+            subject: destination.subject.clone(),
+            jetstream: destination.jetstream,
+            properties: MessageProperties {
+                content_type: Some(properties.content_type).filter(|s| !s.is_empty()),
+                headers: properties.headers,
+                message_id: Some(properties.message_id).filter(|s| !s.is_empty()),
+                correlation_id: Some(properties.correlation_id).filter(|s| !s.is_empty()),
+                ..Default::default()
+            },
+        })
+    }
+}
+
+/// Header values are written to the wire as-is, so a line break would end the header early
+fn ensure_header_value(value: &str) -> Result<()> {
+    ensure!(
+        !value.contains(['\r', '\n']),
+        "nats header values must not contain line breaks"
+    );
+    Ok(())
+}
+
+impl From<NatsDestination> for rpc::NatsDestination {
+    fn from(destination: NatsDestination) -> rpc::NatsDestination {
+        rpc::NatsDestination {
+            subject: destination.subject,
+            jetstream: destination.jetstream,
+            properties: Some(destination.properties.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use async_nats::header::NATS_MESSAGE_ID;
+    use futures::StreamExt;
+    use ulid::Ulid;
+
+    use super::{Nats, NatsDestination, NATS_SINK};
+    use crate::{
+        amqp::MessageProperties, db::DatabaseTask, protos::rpc, sink::Destination, sink::Sink,
+    };
+
+    #[test]
+    fn rejects_invalid_headers() {
+        let destination = |name: &str, value: &str| rpc::NatsDestination {
+            subject: "tasks".to_string(),
+            jetstream: false,
+            properties: Some(rpc::MessageProperties {
+                headers: [(name.to_string(), value.to_string())].into(),
+                ..Default::default()
+            }),
+        };
+        assert!(NatsDestination::try_from(&destination("X-Test", "value")).is_ok());
+        assert!(NatsDestination::try_from(&destination("X:Test", "value")).is_err());
+        assert!(NatsDestination::try_from(&destination("X Test", "value")).is_err());
+        assert!(NatsDestination::try_from(&destination("X-Tést", "value")).is_err());
+        assert!(NatsDestination::try_from(&destination("X-Test", "a\r\nb")).is_err());
+    }
+
+    #[tokio::test]
+    #[ignore = "requires NATS_URL"]
+    async fn publishes_with_headers() {
+        let url = env::var("NATS_URL").unwrap();
+        let subject = format!("task-scheduler-test.{}", Ulid::new());
+        let client = async_nats::connect(&url).await.unwrap();
+        let mut subscriber = client.subscribe(subject.clone()).await.unwrap();
+        client.flush().await.unwrap();
+
+        let task = DatabaseTask {
+            payload: b"hello".to_vec(),
+            ..DatabaseTask::for_test(
+                NATS_SINK,
+                Destination::Nats(NatsDestination {
+                    subject,
+                    jetstream: false,
+                    properties: MessageProperties {
+                        headers: [("X-Test".to_string(), "value".to_string())].into(),
+                        ..Default::default()
+                    },
+                }),
+            )
+        };
+        Nats::new(&url).await.unwrap().deliver(&task).await.unwrap();
+
+        let message = subscriber.next().await.unwrap();
+        assert_eq!(&message.payload[..], b"hello");
+        let headers = message.headers.unwrap();
+        assert_eq!(headers.get("X-Test").unwrap().as_str(), "value");
+        assert_eq!(
+            headers.get(NATS_MESSAGE_ID).unwrap().as_str(),
+            task.id.to_string()
+        );
+    }
+}
@@ -10,7 +10,9 @@ pub static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
 pub mod metrics {
     use std::sync::LazyLock;
 
-    use prometheus::{IntCounter, IntGauge};
+    use prometheus::{
+        Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    };
 
     pub static SCHEDULED_TASKS: LazyLock<IntCounter> = LazyLock::new(|| {
         IntCounter::new("scheduled_tasks", "Scheduled tasks").expect("metric cannot be created")
@@ -20,6 +22,10 @@ pub mod metrics {
         IntCounter::new("cancelled_tasks", "Cancelled tasks").expect("metric cannot be created")
     });
 
+    pub static UPDATED_TASKS: LazyLock<IntCounter> = LazyLock::new(|| {
+        IntCounter::new("updated_tasks", "Updated tasks").expect("metric cannot be created")
+    });
+
     pub static GET_TASKS: LazyLock<IntCounter> = LazyLock::new(|| {
         IntCounter::new("get_tasks", "Get tasks").expect("metric cannot be created")
     });
@@ -37,10 +43,64 @@ pub mod metrics {
         IntCounter::new("failed_tasks", "Failed tasks").expect("metric cannot be created")
     });
 
+    pub static UNROUTABLE_TASKS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+        IntCounterVec::new(
+            Opts::new(
+                "unroutable_tasks",
+                "Tasks returned by the broker as unroutable",
+            ),
+            &["exchange"],
+        )
+        .expect("metric cannot be created")
+    });
+
+    pub static RETRIED_TASKS: LazyLock<IntCounter> = LazyLock::new(|| {
+        IntCounter::new("retried_tasks", "Deliveries rescheduled after a failure")
+            .expect("metric cannot be created")
+    });
+
+    pub static DEAD_TASKS: LazyLock<IntCounter> = LazyLock::new(|| {
+        IntCounter::new("dead_tasks", "Tasks moved to the dead task table")
+            .expect("metric cannot be created")
+    });
+
+    pub static BATCH_SIZE: LazyLock<Histogram> = LazyLock::new(|| {
+        Histogram::with_opts(
+            HistogramOpts::new("delivery_batch_size", "Tasks claimed per delivery batch").buckets(
+                vec![0.0, 1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0],
+            ),
+        )
+        .expect("metric cannot be created")
+    });
+
+    pub static BATCH_DURATION: LazyLock<Histogram> = LazyLock::new(|| {
+        Histogram::with_opts(HistogramOpts::new(
+            "delivery_batch_duration_seconds",
+            "Time taken to deliver a batch of tasks",
+        ))
+        .expect("metric cannot be created")
+    });
+
     pub static TOTAL_TASKS: LazyLock<IntGauge> = LazyLock::new(|| {
         IntGauge::new("total_tasks", "Total tasks").expect("metric cannot be created")
     });
 
+    pub static AMQP_CONNECTED: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+        IntGaugeVec::new(
+            Opts::new("amqp_connected", "Whether the AMQP connection is up"),
+            &["sink"],
+        )
+        .expect("metric cannot be created")
+    });
+
+    pub static SCHEDULER_IS_LEADER: LazyLock<IntGauge> = LazyLock::new(|| {
+        IntGauge::new(
+            "scheduler_is_leader",
+            "Whether this instance is running the scheduler loop",
+        )
+        .expect("metric cannot be created")
+    });
+
     pub static BULK_TASK_REQUESTS: LazyLock<IntGauge> = LazyLock::new(|| {
         IntGauge::new("bulk_task_requests", "Bulk task requests").expect("metric cannot be created")
     });
@@ -53,18 +113,42 @@ fn register_custom_metrics() {
     REGISTRY
         .register(Box::new(metrics::CANCELLED_TASKS.clone()))
         .expect("metric cannot be registered");
+    REGISTRY
+        .register(Box::new(metrics::UPDATED_TASKS.clone()))
+        .expect("metric cannot be registered");
     REGISTRY
         .register(Box::new(metrics::PROCESSED_TASKS.clone()))
         .expect("metric cannot be registered");
     REGISTRY
         .register(Box::new(metrics::GET_TASKS.clone()))
         .expect("metric cannot be registered");
+    REGISTRY
+        .register(Box::new(metrics::UNROUTABLE_TASKS.clone()))
+        .expect("metric cannot be registered");
+    REGISTRY
+        .register(Box::new(metrics::RETRIED_TASKS.clone()))
+        .expect("metric cannot be registered");
+    REGISTRY
+        .register(Box::new(metrics::DEAD_TASKS.clone()))
+        .expect("metric cannot be registered");
+    REGISTRY
+        .register(Box::new(metrics::BATCH_SIZE.clone()))
+        .expect("metric cannot be registered");
+    REGISTRY
+        .register(Box::new(metrics::BATCH_DURATION.clone()))
+        .expect("metric cannot be registered");
     REGISTRY
         .register(Box::new(metrics::TOTAL_TASKS.clone()))
         .expect("metric cannot be registered");
     REGISTRY
         .register(Box::new(BULK_TASK_REQUESTS.clone()))
         .expect("metric cannot be registered");
+    REGISTRY
+        .register(Box::new(metrics::AMQP_CONNECTED.clone()))
+        .expect("metric cannot be registered");
+    REGISTRY
+        .register(Box::new(metrics::SCHEDULER_IS_LEADER.clone()))
+        .expect("metric cannot be registered");
 
     debug!("Registered custom metrics");
 }
@@ -0,0 +1,145 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+
+use crate::protos::rpc;
+
+/// A cron expression (with seconds) evaluated in a specific time zone
+pub struct CronSchedule {
+    schedule: Schedule,
+    timezone: Tz,
+}
+
+impl CronSchedule {
+    /// Parses a cron expression, defaulting to UTC when no time zone is given
+    pub fn parse(expression: &str, timezone: Option<&str>) -> Result<Self> {
+        let schedule = Schedule::from_str(expression)?;
+        let timezone = match timezone {
+            Some(tz) => Tz::from_str(tz).map_err(|e| anyhow!("Invalid time zone: {}", e))?,
+            None => Tz::UTC,
+        };
+        Ok(Self { schedule, timezone })
+    }
+
+    /// Returns the first occurrence strictly after the given time
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule
+            .after(&after.with_timezone(&self.timezone))
+            .next()
+            .map(|next| next.with_timezone(&Utc))
+    }
+}
+
+/// How a recurring task repeats
+pub enum Recurrence {
+    Cron(Box<CronSchedule>),
+    Interval(TimeDelta),
+}
+
+impl Recurrence {
+    /// Returns the first occurrence strictly after `after`. Interval occurrences stay aligned to
+    /// `previous`, the run that just happened.
+    pub fn next_after(
+        &self,
+        previous: DateTime<Utc>,
+        after: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        match self {
+            Recurrence::Cron(schedule) => schedule.next_after(after),
+            Recurrence::Interval(interval) => {
+                let interval_ms = interval.num_milliseconds();
+                if interval_ms <= 0 {
+                    return None;
+                }
+                let elapsed_ms = after.signed_duration_since(previous).num_milliseconds();
+                let missed = elapsed_ms.max(0) / interval_ms;
+                previous.checked_add_signed(TimeDelta::milliseconds(interval_ms * (missed + 1)))
+            }
+        }
+    }
+}
+
+/// What to do with occurrences of a recurring task that were missed, e.g. while the scheduler
+/// was down
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MisfirePolicy {
+    /// Run once for all missed occurrences, then continue from the next future occurrence
+    #[default]
+    FireOnce,
+    /// Run every missed occurrence in order
+    FireAll,
+    /// Drop missed occurrences and continue from the next future occurrence
+    Skip,
+}
+
+impl From<MisfirePolicy> for i16 {
+    fn from(policy: MisfirePolicy) -> i16 {
+        match policy {
+            MisfirePolicy::FireOnce => 0,
+            MisfirePolicy::FireAll => 1,
+            MisfirePolicy::Skip => 2,
+        }
+    }
+}
+
+impl TryFrom<i16> for MisfirePolicy {
+    type Error = anyhow::Error;
+    fn try_from(value: i16) -> Result<Self> {
+        match value {
+            0 => Ok(MisfirePolicy::FireOnce),
+            1 => Ok(MisfirePolicy::FireAll),
+            2 => Ok(MisfirePolicy::Skip),
+            _ => Err(anyhow!("Unknown misfire policy: {}", value)),
+        }
+    }
+}
+
+impl From<rpc::MisfirePolicy> for MisfirePolicy {
+    fn from(policy: rpc::MisfirePolicy) -> MisfirePolicy {
+        match policy {
+            rpc::MisfirePolicy::FireOnce => MisfirePolicy::FireOnce,
+            rpc::MisfirePolicy::FireAll => MisfirePolicy::FireAll,
+            rpc::MisfirePolicy::Skip => MisfirePolicy::Skip,
+        }
+    }
+}
+
+impl From<MisfirePolicy> for rpc::MisfirePolicy {
+    fn from(policy: MisfirePolicy) -> rpc::MisfirePolicy {
+        match policy {
+            MisfirePolicy::FireOnce => rpc::MisfirePolicy::FireOnce,
+            MisfirePolicy::FireAll => rpc::MisfirePolicy::FireAll,
+            MisfirePolicy::Skip => rpc::MisfirePolicy::Skip,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, TimeDelta, Utc};
+
+    use super::{CronSchedule, Recurrence};
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn interval_stays_aligned_to_previous_run() {
+        let recurrence = Recurrence::Interval(TimeDelta::seconds(10));
+        assert_eq!(recurrence.next_after(at(0), at(0)), Some(at(10)));
+        assert_eq!(recurrence.next_after(at(0), at(25)), Some(at(30)));
+        assert_eq!(recurrence.next_after(at(0), at(30)), Some(at(40)));
+    }
+
+    #[test]
+    fn cron_is_evaluated_in_its_time_zone() {
+        let schedule = CronSchedule::parse("0 0 9 * * *", Some("America/New_York")).unwrap();
+        let after = "2026-07-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let expected = "2026-07-01T13:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(schedule.next_after(after), Some(expected));
+    }
+}
@@ -0,0 +1,181 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, ensure, Result};
+use async_trait::async_trait;
+use redis::{
+    aio::{ConnectionManager, ConnectionManagerConfig},
+    AsyncCommands, Client,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::{
+    db::DatabaseTask,
+    protos::rpc,
+    sink::{Destination, Sink},
+};
+
+/// Registered when `REDIS_URL` is set
+pub const REDIS_SINK: &str = "redis";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedisCommand {
+    /// Append to a stream
+    Xadd,
+    /// Publish to a pub/sub channel
+    Publish,
+}
+
+/// Sends a task's payload to a Redis stream or channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisDestination {
+    pub command: RedisCommand,
+    /// Stream key or channel name
+    pub key: String,
+}
+
+pub struct Redis {
+    connection: ConnectionManager,
+}
+
+impl Redis {
+    /// Connects to Redis, timing out connection attempts and commands after `timeout`
+    pub async fn new(url: &str, timeout: Duration) -> Result<Self> {
+        info!("Connecting to Redis at {}", url);
+        let client = Client::open(url)?;
+        let config = ConnectionManagerConfig::new()
+            .set_connection_timeout(timeout)
+            .set_response_timeout(timeout);
+        Ok(Self {
+            connection: ConnectionManager::new_with_config(client, config).await?,
+        })
+    }
+}
+
+#[async_trait]
+impl Sink for Redis {
+    async fn deliver(&self, task: &DatabaseTask) -> Result<()> {
+        let Destination::Redis(destination) = &task.destination else {
+            return Err(anyhow!("Task {} is not a Redis task", task.id));
+        };
+        debug!(
+            "Sending {:?} to Redis key {}",
+            destination.command, destination.key
+        );
+
+        let mut connection = self.connection.clone();
+        match destination.command {
+            RedisCommand::Xadd => {
+                let task_id = task.id.to_string();
+                let _: String = connection
+                    .xadd(
+                        &destination.key,
+                        "*",
+                        &[
+                            ("task_id", task_id.as_bytes()),
+                            ("payload", &task.payload[..]),
+                        ],
+                    )
+                    .await?;
+            }
+            RedisCommand::Publish => {
+                let _: i64 = connection
+                    .publish(&destination.key, &task.payload[..])
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn accepts(&self, destination: &Destination) -> bool {
+        matches!(destination, Destination::Redis(_))
+    }
+}
+
+impl From<rpc::RedisCommand> for RedisCommand {
+    fn from(command: rpc::RedisCommand) -> RedisCommand {
+        match command {
+            rpc::RedisCommand::Xadd => RedisCommand::Xadd,
+            rpc::RedisCommand::Publish => RedisCommand::Publish,
+        }
+    }
+}
+
+impl From<RedisCommand> for rpc::RedisCommand {
+    fn from(command: RedisCommand) -> rpc::RedisCommand {
+        match command {
+            RedisCommand::Xadd => rpc::RedisCommand::Xadd,
+            RedisCommand::Publish => rpc::RedisCommand::Publish,
+        }
+    }
+}
+
+impl TryFrom<&rpc::RedisDestination> for RedisDestination {
+    type Error = anyhow::Error;
+    fn try_from(destination: &rpc::RedisDestination) -> Result<Self> {
+        let command = rpc::RedisCommand::try_from(destination.command)
+            .map_err(|_| anyhow!("invalid redis command"))?;
+        ensure!(!destination.key.is_empty(), "redis key is required");
+        Ok(RedisDestination {
+            command: command.into(),
+            key: destination.key.clone(),
+        })
+    }
+}
+
+impl From<RedisDestination> for rpc::RedisDestination {
+    fn from(destination: RedisDestination) -> rpc::RedisDestination {
+        rpc::RedisDestination {
+            command: rpc::RedisCommand::from(destination.command).into(),
+            key: destination.key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, time::Duration};
+
+    use redis::{streams::StreamRangeReply, AsyncCommands, Client};
+    use ulid::Ulid;
+
+    use super::{Redis, RedisCommand, RedisDestination, REDIS_SINK};
+    use crate::{db::DatabaseTask, sink::Destination, sink::Sink};
+
+    #[tokio::test]
+    #[ignore = "requires REDIS_URL"]
+    async fn appends_to_stream() {
+        let url = env::var("REDIS_URL").unwrap();
+        let key = format!("task-scheduler-test-{}", Ulid::new());
+        let task = DatabaseTask {
+            payload: b"hello".to_vec(),
+            ..DatabaseTask::for_test(
+                REDIS_SINK,
+                Destination::Redis(RedisDestination {
+                    command: RedisCommand::Xadd,
+                    key: key.clone(),
+                }),
+            )
+        };
+
+        Redis::new(&url, Duration::from_secs(30))
+            .await
+            .unwrap()
+            .deliver(&task)
+            .await
+            .unwrap();
+
+        let mut connection = Client::open(url.as_str())
+            .unwrap()
+            .get_multiplexed_async_connection()
+            .await
+            .unwrap();
+        let reply: StreamRangeReply = connection.xrange_all(&key).await.unwrap();
+        let _: () = connection.del(&key).await.unwrap();
+        assert_eq!(reply.ids.len(), 1);
+        let entry = &reply.ids[0];
+        assert_eq!(entry.get::<String>("task_id"), Some(task.id.to_string()));
+        assert_eq!(entry.get::<Vec<u8>>("payload"), Some(b"hello".to_vec()));
+    }
+}
@@ -0,0 +1,71 @@
+use std::env;
+
+use anyhow::{ensure, Result};
+use chrono::TimeDelta;
+
+/// Controls how failed deliveries are retried
+pub struct RetryPolicy {
+    /// Total delivery attempts before a task is given up on
+    pub max_attempts: i32,
+    pub base_delay: TimeDelta,
+    pub max_delay: TimeDelta,
+}
+
+impl RetryPolicy {
+    /// Reads the retry policy from `RETRY_MAX_ATTEMPTS`, `RETRY_BASE_DELAY_MS` and
+    /// `RETRY_MAX_DELAY_MS`
+    pub fn from_env() -> Result<Self> {
+        let max_attempts = env::var("RETRY_MAX_ATTEMPTS")
+            .unwrap_or("5".to_string())
+            .parse::<i32>()?;
+        let base_delay = env::var("RETRY_BASE_DELAY_MS")
+            .unwrap_or("1000".to_string())
+            .parse::<i64>()?;
+        let max_delay = env::var("RETRY_MAX_DELAY_MS")
+            .unwrap_or("300000".to_string())
+            .parse::<i64>()?;
+        ensure!(max_attempts > 0, "RETRY_MAX_ATTEMPTS must be positive");
+        ensure!(base_delay >= 0, "RETRY_BASE_DELAY_MS must not be negative");
+        ensure!(
+            max_delay >= base_delay,
+            "RETRY_MAX_DELAY_MS must not be less than RETRY_BASE_DELAY_MS"
+        );
+
+        Ok(Self {
+            max_attempts,
+            base_delay: TimeDelta::milliseconds(base_delay),
+            max_delay: TimeDelta::milliseconds(max_delay),
+        })
+    }
+
+    /// Returns how long to wait before retrying after the given number of failed attempts
+    pub fn backoff(&self, attempts: i32) -> TimeDelta {
+        let exponent = attempts.saturating_sub(1).clamp(0, 32) as u32;
+        let delay_ms = self
+            .base_delay
+            .num_milliseconds()
+            .saturating_mul(1 << exponent);
+        TimeDelta::milliseconds(delay_ms).min(self.max_delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::RetryPolicy;
+
+    #[test]
+    fn backoff_doubles_until_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 100,
+            base_delay: TimeDelta::seconds(1),
+            max_delay: TimeDelta::seconds(10),
+        };
+        assert_eq!(policy.backoff(1), TimeDelta::seconds(1));
+        assert_eq!(policy.backoff(2), TimeDelta::seconds(2));
+        assert_eq!(policy.backoff(4), TimeDelta::seconds(8));
+        assert_eq!(policy.backoff(5), TimeDelta::seconds(10));
+        assert_eq!(policy.backoff(99), TimeDelta::seconds(10));
+    }
+}
@@ -1,18 +1,37 @@
-use std::sync::Arc;
+use std::{pin::Pin, sync::Arc};
 
-use chrono::DateTime;
+use chrono::{DateTime, TimeDelta, Utc};
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    Stream, StreamExt,
+};
 use tonic::{Request, Response, Status};
+use tracing::{error, warn};
 use ulid::Ulid;
 
 use crate::{
-    db::{Database, DatabaseTask},
-    prometheus::metrics::{CANCELLED_TASKS, SCHEDULED_TASKS},
+    amqp::{AmqpDestination, MessageProperties},
+    db::{Database, DatabaseTask, TaskCursor, TaskFilter, TaskOrder},
+    http::HttpDestination,
+    kafka::KafkaDestination,
+    nats::NatsDestination,
+    prometheus::metrics::{CANCELLED_TASKS, SCHEDULED_TASKS, UPDATED_TASKS},
     protos::rpc::{
-        task_scheduler_server::TaskScheduler, BulkTaskRequest, BulkTaskResponse, CancelTaskRequest,
-        CancelTaskResponse, GetTaskRequest, ScheduleTaskRequest, ScheduleTaskResponse, Task,
+        self, task_scheduler_server::TaskScheduler, BulkTaskRequest, BulkTaskResponse,
+        CancelManyTasksRequest, CancelManyTasksResponse, CancelTaskRequest, CancelTaskResponse,
+        GetTaskHistoryRequest, GetTaskHistoryResponse, GetTaskRequest, ListDeadTasksRequest,
+        ListDeadTasksResponse, ListTasksRequest, ListTasksResponse, PurgeDeadTasksRequest,
+        PurgeDeadTasksResponse, RequeueDeadTaskRequest, RequeueDeadTaskResponse,
+        ScheduleManyTasksRequest, ScheduleManyTasksResponse, ScheduleManyTasksResult,
+        ScheduleTaskRequest, ScheduleTaskResponse, Task, UpdateTaskRequest, WatchTasksRequest,
     },
+    recurrence::{CronSchedule, MisfirePolicy, Recurrence},
+    redis::RedisDestination,
+    sink::Destination,
 };
 
+const MAX_SCHEDULE_MANY_TASKS: usize = 10_000;
+
 pub struct RpcServer {
     db: Arc<Database>,
 }
@@ -31,48 +50,200 @@ impl TaskScheduler for RpcServer {
     ) -> Result<Response<ScheduleTaskResponse>, Status> {
         SCHEDULED_TASKS.inc();
 
+        let task = self.task_from_request(request.get_ref())?;
+
+        match self.db.schedule(&task).await {
+            Ok(task_id) => Ok(Response::new(ScheduleTaskResponse {
+                task_id: task_id.to_bytes().to_vec(),
+            })),
+            Err(e) => {
+                error!("Failed to schedule task: {:?}", e);
+                Err(Status::internal("Failed to schedule task"))
+            }
+        }
+    }
+
+    async fn schedule_many_tasks(
+        &self,
+        request: Request<ScheduleManyTasksRequest>,
+    ) -> Result<Response<ScheduleManyTasksResponse>, Status> {
         let request = request.get_ref();
-        let run_at = request
-            .run_at
-            .ok_or_else(|| Status::invalid_argument("run_at is required"))?;
-        let run_at = DateTime::from_timestamp(run_at.seconds, run_at.nanos as u32)
-            .ok_or_else(|| Status::invalid_argument("invalid timestamp"))?;
+        if request.tasks.len() > MAX_SCHEDULE_MANY_TASKS {
+            return Err(Status::invalid_argument(format!(
+                "at most {} tasks can be scheduled at once",
+                MAX_SCHEDULE_MANY_TASKS
+            )));
+        }
 
-        let task = DatabaseTask {
-            id: Ulid::new(),
-            exchange: Some(request.exchange.clone()),
-            routing_key: request.routing_key.clone(),
-            run_at,
-            payload: request.payload.clone(),
+        let mut tasks = Vec::with_capacity(request.tasks.len());
+        let mut results = Vec::with_capacity(request.tasks.len());
+        for (index, task) in request.tasks.iter().enumerate() {
+            match self.task_from_request(task) {
+                Ok(task) => {
+                    tasks.push(task);
+                    results.push(None);
+                }
+                Err(status) if request.best_effort => {
+                    results.push(Some(status.message().to_string()));
+                }
+                Err(status) => {
+                    return Err(Status::invalid_argument(format!(
+                        "task {}: {}",
+                        index,
+                        status.message()
+                    )))
+                }
+            }
+        }
+
+        let mut task_ids = match self.db.schedule_many(&tasks).await {
+            Ok(task_ids) => task_ids.into_iter(),
+            Err(e) => {
+                error!("Failed to schedule tasks: {:?}", e);
+                return Err(Status::internal("Failed to schedule tasks"));
+            }
         };
+        SCHEDULED_TASKS.inc_by(tasks.len() as u64);
 
-        if self.db.schedule(&task).await.is_ok() {
-            Ok(Response::new(ScheduleTaskResponse {
-                task_id: task.id.to_bytes().to_vec(),
-            }))
-        } else {
-            Err(Status::internal("Failed to schedule task"))
-        }
+        let results = results
+            .into_iter()
+            .map(|error| match error {
+                Some(error) => ScheduleManyTasksResult {
+                    task_id: Vec::new(),
+                    error,
+                },
+                None => ScheduleManyTasksResult {
+                    task_id: task_ids
+                        .next()
+                        .map(|task_id| task_id.to_bytes().to_vec())
+                        .unwrap_or_default(),
+                    error: String::new(),
+                },
+            })
+            .collect();
+        Ok(Response::new(ScheduleManyTasksResponse { results }))
     }
 
     async fn cancel_task(
         &self,
         request: Request<CancelTaskRequest>,
     ) -> Result<Response<CancelTaskResponse>, Status> {
-        CANCELLED_TASKS.inc();
         let req = request.get_ref();
         let task_id = self.get_task_id(&req.task_id)?;
 
-        if self.db.get_task(task_id).await.is_none() {
-            return Err(Status::not_found("Task not found"));
+        match self.db.cancel_task(task_id).await {
+            Ok(true) => {
+                CANCELLED_TASKS.inc();
+                Ok(Response::new(CancelTaskResponse {
+                    task_id: task_id.to_bytes().to_vec(),
+                }))
+            }
+            Ok(false) => Err(Status::not_found("Task not found")),
+            Err(_) => Err(Status::internal("Failed to cancel task")),
         }
+    }
 
-        if self.db.cancel_task(task_id).await.is_ok() {
-            Ok(Response::new(CancelTaskResponse {
-                task_id: task_id.to_bytes().to_vec(),
-            }))
+    async fn cancel_many_tasks(
+        &self,
+        request: Request<CancelManyTasksRequest>,
+    ) -> Result<Response<CancelManyTasksResponse>, Status> {
+        let request = request.get_ref();
+        let cancelled = if !request.task_id.is_empty() {
+            let task_ids = request
+                .task_id
+                .iter()
+                .map(|id| self.get_task_id(id))
+                .collect::<Result<Vec<_>, _>>()?;
+            self.db.cancel_many_tasks(&task_ids).await
         } else {
-            Err(Status::internal("Failed to cancel task"))
+            let filter = request
+                .filter
+                .as_ref()
+                .map(parse_filter)
+                .transpose()?
+                .unwrap_or_default();
+            // An empty filter would cancel every task
+            if filter.is_empty() {
+                return Err(Status::invalid_argument("task_id or filter is required"));
+            }
+            self.db.cancel_tasks_matching(&filter).await
+        };
+
+        let cancelled = cancelled.map_err(|_| Status::internal("Failed to cancel tasks"))?;
+        CANCELLED_TASKS.inc_by(cancelled.len() as u64);
+        Ok(Response::new(CancelManyTasksResponse {
+            task_id: cancelled
+                .into_iter()
+                .map(|id| id.to_bytes().to_vec())
+                .collect(),
+        }))
+    }
+
+    async fn update_task(
+        &self,
+        request: Request<UpdateTaskRequest>,
+    ) -> Result<Response<Task>, Status> {
+        let request = request.into_inner();
+        let task_id = self.get_task_id(&request.task_id)?;
+        let run_at = request.run_at.map(parse_timestamp).transpose()?;
+        if let Some(exchange) = &request.exchange {
+            check_short_string("exchange", exchange)?;
+        }
+        if let Some(routing_key) = &request.routing_key {
+            check_short_string("routing_key", routing_key)?;
+        }
+        let properties = request
+            .properties
+            .as_ref()
+            .map(parse_properties)
+            .transpose()?;
+
+        let updated = self
+            .db
+            .update_task(task_id, |task| {
+                if let Some(run_at) = run_at {
+                    task.run_at = run_at;
+                }
+                if let Some(payload) = request.payload {
+                    task.payload = payload;
+                }
+                let amqp_fields_set = request.exchange.is_some()
+                    || request.routing_key.is_some()
+                    || properties.is_some();
+                match &mut task.destination {
+                    Destination::Amqp(destination) => {
+                        if let Some(exchange) = request.exchange {
+                            destination.exchange = Some(exchange);
+                        }
+                        if let Some(routing_key) = request.routing_key {
+                            destination.routing_key = routing_key;
+                        }
+                        if let Some(properties) = properties {
+                            destination.properties = properties;
+                        }
+                    }
+                    _ if amqp_fields_set => {
+                        return Err(Status::invalid_argument(
+                            "exchange, routing_key and properties only apply to AMQP tasks",
+                        )
+                        .into())
+                    }
+                    _ => {}
+                }
+                Ok(())
+            })
+            .await
+            .map_err(|e| match e.downcast::<Status>() {
+                Ok(status) => status,
+                Err(_) => Status::internal("Failed to update task"),
+            })?;
+
+        match updated {
+            Some(task) => {
+                UPDATED_TASKS.inc();
+                Ok(Response::new(task.into()))
+            }
+            None => Err(Status::not_found("Task not found")),
         }
     }
 
@@ -107,12 +278,442 @@ impl TaskScheduler for RpcServer {
             Err(Status::not_found("Task not found"))
         }
     }
+
+    async fn list_tasks(
+        &self,
+        request: Request<ListTasksRequest>,
+    ) -> Result<Response<ListTasksResponse>, Status> {
+        let request = request.get_ref();
+        let filter = request
+            .filter
+            .as_ref()
+            .map(parse_filter)
+            .transpose()?
+            .unwrap_or_default();
+        let order = rpc::TaskOrder::try_from(request.order)
+            .map_err(|_| Status::invalid_argument("invalid order"))?;
+        let after = if request.after.is_empty() {
+            None
+        } else {
+            Some(parse_cursor(&request.after)?)
+        };
+        let limit = match request.limit {
+            0 => 100,
+            limit => limit.min(1000),
+        };
+
+        let tasks = self
+            .db
+            .list_tasks(&filter, TaskOrder::from(order), after, limit as i64)
+            .await
+            .map_err(|_| Status::internal("Failed to list tasks"))?;
+
+        let next_cursor = match tasks.last() {
+            Some(last) if tasks.len() == limit as usize => encode_cursor(TaskCursor {
+                run_at: last.run_at,
+                id: last.id,
+            }),
+            _ => Vec::new(),
+        };
+
+        Ok(Response::new(ListTasksResponse {
+            tasks: tasks.into_iter().map(Into::into).collect(),
+            next_cursor,
+        }))
+    }
+
+    type WatchTasksStream = Pin<Box<dyn Stream<Item = Result<rpc::TaskEvent, Status>> + Send>>;
+
+    async fn watch_tasks(
+        &self,
+        request: Request<WatchTasksRequest>,
+    ) -> Result<Response<Self::WatchTasksStream>, Status> {
+        let filter = request
+            .get_ref()
+            .filter
+            .as_ref()
+            .map(parse_filter)
+            .transpose()?
+            .unwrap_or_default();
+
+        let events =
+            BroadcastStream::new(self.db.subscribe()).filter_map(move |event| match event {
+                Ok(event) if filter.matches(&event.task) => Some(Ok(event.into())),
+                Ok(_) => None,
+                Err(BroadcastStreamRecvError::Lagged(missed)) => {
+                    warn!("Task watcher fell behind by {} events", missed);
+                    Some(Err(Status::resource_exhausted(format!(
+                        "missed {} events",
+                        missed
+                    ))))
+                }
+            });
+        Ok(Response::new(Box::pin(events)))
+    }
+
+    async fn get_task_history(
+        &self,
+        request: Request<GetTaskHistoryRequest>,
+    ) -> Result<Response<GetTaskHistoryResponse>, Status> {
+        let request = request.get_ref();
+        let task_id = self.get_task_id(&request.task_id)?;
+        let after = match request.after {
+            0 => None,
+            after => {
+                Some(i64::try_from(after).map_err(|_| Status::invalid_argument("invalid cursor"))?)
+            }
+        };
+        let limit = match request.limit {
+            0 => 100,
+            limit => limit.min(1000),
+        };
+
+        let entries = self
+            .db
+            .get_task_history(task_id, after, limit as i64)
+            .await
+            .map_err(|_| Status::internal("Failed to get task history"))?;
+
+        let next_cursor = match entries.last() {
+            Some(last) if entries.len() == limit as usize => last.id as u64,
+            _ => 0,
+        };
+
+        Ok(Response::new(GetTaskHistoryResponse {
+            entries: entries.into_iter().map(Into::into).collect(),
+            next_cursor,
+        }))
+    }
+
+    async fn list_dead_tasks(
+        &self,
+        request: Request<ListDeadTasksRequest>,
+    ) -> Result<Response<ListDeadTasksResponse>, Status> {
+        let request = request.get_ref();
+        let after = if request.after.is_empty() {
+            None
+        } else {
+            Some(self.get_task_id(&request.after)?)
+        };
+        let limit = match request.limit {
+            0 => 100,
+            limit => limit.min(1000),
+        };
+
+        let tasks = self
+            .db
+            .get_dead_tasks(after, limit as i64)
+            .await
+            .map_err(|_| Status::internal("Failed to list dead tasks"))?;
+
+        let next_cursor = match tasks.last() {
+            Some(last) if tasks.len() == limit as usize => last.task.id.to_bytes().to_vec(),
+            _ => Vec::new(),
+        };
+
+        Ok(Response::new(ListDeadTasksResponse {
+            tasks: tasks.into_iter().map(Into::into).collect(),
+            next_cursor,
+        }))
+    }
+
+    async fn get_dead_task(
+        &self,
+        request: Request<GetTaskRequest>,
+    ) -> Result<Response<rpc::DeadTask>, Status> {
+        let task_id = self.get_task_id(&request.get_ref().task_id)?;
+        if let Some(dead) = self.db.get_dead_task(task_id).await {
+            Ok(Response::new(dead.into()))
+        } else {
+            Err(Status::not_found("Task not found"))
+        }
+    }
+
+    async fn requeue_dead_task(
+        &self,
+        request: Request<RequeueDeadTaskRequest>,
+    ) -> Result<Response<RequeueDeadTaskResponse>, Status> {
+        let request = request.get_ref();
+        let task_id = self.get_task_id(&request.task_id)?;
+        let run_at = match request.run_at {
+            Some(run_at) => parse_timestamp(run_at)?,
+            None => Utc::now(),
+        };
+
+        match self.db.requeue_dead_task(task_id, run_at).await {
+            Ok(true) => Ok(Response::new(RequeueDeadTaskResponse {
+                task_id: task_id.to_bytes().to_vec(),
+            })),
+            Ok(false) => Err(Status::not_found("Task not found")),
+            Err(_) => Err(Status::internal("Failed to requeue task")),
+        }
+    }
+
+    async fn purge_dead_tasks(
+        &self,
+        request: Request<PurgeDeadTasksRequest>,
+    ) -> Result<Response<PurgeDeadTasksResponse>, Status> {
+        let request = request.get_ref();
+        let purged = if !request.task_id.is_empty() {
+            let task_ids = request
+                .task_id
+                .iter()
+                .map(|id| self.get_task_id(id))
+                .collect::<Result<Vec<_>, _>>()?;
+            self.db.purge_dead_tasks(&task_ids).await
+        } else if let Some(failed_before) = request.failed_before {
+            self.db
+                .purge_dead_tasks_before(parse_timestamp(failed_before)?)
+                .await
+        } else {
+            return Err(Status::invalid_argument(
+                "task_id or failed_before is required",
+            ));
+        };
+
+        let purged = purged.map_err(|_| Status::internal("Failed to purge dead tasks"))?;
+        Ok(Response::new(PurgeDeadTasksResponse { purged }))
+    }
 }
 
 impl RpcServer {
+    fn task_from_request(&self, request: &ScheduleTaskRequest) -> Result<DatabaseTask, Status> {
+        let cron = Some(request.cron.clone()).filter(|cron| !cron.is_empty());
+        let timezone = Some(request.timezone.clone()).filter(|tz| !tz.is_empty());
+        let interval = request
+            .interval
+            .map(|interval| {
+                // Intervals are stored in milliseconds
+                TimeDelta::new(interval.seconds, interval.nanos as u32)
+                    .filter(|interval| {
+                        *interval >= TimeDelta::milliseconds(1)
+                            && interval.subsec_nanos() % 1_000_000 == 0
+                    })
+                    .ok_or_else(|| {
+                        Status::invalid_argument(
+                            "interval must be a positive whole number of milliseconds",
+                        )
+                    })
+            })
+            .transpose()?;
+
+        let recurrence = match (&cron, interval) {
+            (Some(_), Some(_)) => {
+                return Err(Status::invalid_argument(
+                    "cron and interval are mutually exclusive",
+                ))
+            }
+            (Some(cron), None) => {
+                let schedule = CronSchedule::parse(cron, timezone.as_deref()).map_err(|e| {
+                    Status::invalid_argument(format!("invalid cron schedule: {}", e))
+                })?;
+                Some(Recurrence::Cron(Box::new(schedule)))
+            }
+            (None, Some(interval)) => Some(Recurrence::Interval(interval)),
+            (None, None) => None,
+        };
+
+        let run_at = match (request.run_at, &recurrence) {
+            (Some(run_at), _) => parse_timestamp(run_at)?,
+            (None, Some(recurrence)) => {
+                let now = Utc::now();
+                recurrence.next_after(now, now).ok_or_else(|| {
+                    Status::invalid_argument("schedule has no upcoming occurrences")
+                })?
+            }
+            (None, None) => return Err(Status::invalid_argument("run_at is required")),
+        };
+
+        if request.idempotency_key.len() > 255 {
+            return Err(Status::invalid_argument(
+                "idempotency_key must be at most 255 bytes",
+            ));
+        }
+
+        let misfire_policy = rpc::MisfirePolicy::try_from(request.misfire_policy)
+            .map_err(|_| Status::invalid_argument("invalid misfire_policy"))?;
+
+        let destination = match &request.destination {
+            Some(rpc::schedule_task_request::Destination::Http(http)) => Destination::Http(
+                HttpDestination::try_from(http)
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?,
+            ),
+            Some(rpc::schedule_task_request::Destination::Redis(redis)) => Destination::Redis(
+                RedisDestination::try_from(redis)
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?,
+            ),
+            Some(rpc::schedule_task_request::Destination::Nats(nats)) => Destination::Nats(
+                NatsDestination::try_from(nats)
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?,
+            ),
+            Some(rpc::schedule_task_request::Destination::Kafka(kafka)) => Destination::Kafka(
+                KafkaDestination::try_from(kafka)
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?,
+            ),
+            None => {
+                check_short_string("exchange", &request.exchange)?;
+                check_short_string("routing_key", &request.routing_key)?;
+                Destination::Amqp(AmqpDestination {
+                    exchange: Some(request.exchange.clone()),
+                    routing_key: request.routing_key.clone(),
+                    properties: request
+                        .properties
+                        .as_ref()
+                        .map(parse_properties)
+                        .transpose()?
+                        .unwrap_or_default(),
+                    mandatory: request.mandatory,
+                })
+            }
+        };
+
+        let sink = match request.sink.as_str() {
+            "" => destination.default_sink(),
+            sink => sink,
+        };
+        match self.db.sinks().get(sink) {
+            Some(registered) if registered.accepts(&destination) => {}
+            Some(_) => {
+                return Err(Status::invalid_argument(format!(
+                    "sink {} cannot deliver to this destination",
+                    sink
+                )))
+            }
+            None => return Err(Status::invalid_argument(format!("unknown sink {}", sink))),
+        }
+
+        Ok(DatabaseTask {
+            id: Ulid::new(),
+            sink: sink.to_string(),
+            destination,
+            run_at,
+            payload: request.payload.clone(),
+            cron,
+            timezone,
+            interval,
+            max_runs: Some(
+                i32::try_from(request.max_runs)
+                    .map_err(|_| Status::invalid_argument("max_runs is too large"))?,
+            )
+            .filter(|max_runs| *max_runs > 0),
+            until: request.until.map(parse_timestamp).transpose()?,
+            run_count: 0,
+            misfire_policy: MisfirePolicy::from(misfire_policy),
+            attempts: 0,
+            occurrence_at: None,
+            idempotency_key: Some(request.idempotency_key.clone()).filter(|key| !key.is_empty()),
+            labels: request.labels.clone(),
+        })
+    }
+
     fn get_task_id(&self, task_id: &[u8]) -> Result<Ulid, Status> {
         Ok(Ulid::from_bytes(task_id.try_into().map_err(|_| {
             Status::invalid_argument("Invalid task_id")
         })?))
     }
 }
+
+fn parse_timestamp(timestamp: prost_types::Timestamp) -> Result<DateTime<Utc>, Status> {
+    DateTime::from_timestamp(timestamp.seconds, timestamp.nanos as u32)
+        .ok_or_else(|| Status::invalid_argument("invalid timestamp"))
+}
+
+/// Encodes a cursor as the task ID followed by its run time in microseconds
+fn encode_cursor(cursor: TaskCursor) -> Vec<u8> {
+    let mut bytes = cursor.id.to_bytes().to_vec();
+    bytes.extend_from_slice(&cursor.run_at.timestamp_micros().to_be_bytes());
+    bytes
+}
+
+fn parse_cursor(cursor: &[u8]) -> Result<TaskCursor, Status> {
+    let invalid = || Status::invalid_argument("invalid cursor");
+    if cursor.len() != 24 {
+        return Err(invalid());
+    }
+    let (id, run_at) = cursor.split_at(16);
+    let id = Ulid::from_bytes(id.try_into().map_err(|_| invalid())?);
+    let run_at = i64::from_be_bytes(run_at.try_into().map_err(|_| invalid())?);
+    Ok(TaskCursor {
+        run_at: DateTime::from_timestamp_micros(run_at).ok_or_else(invalid)?,
+        id,
+    })
+}
+
+fn parse_filter(filter: &rpc::TaskFilter) -> Result<TaskFilter, Status> {
+    Ok(TaskFilter {
+        routing_key: filter.routing_key.clone(),
+        exchange: filter.exchange.clone(),
+        run_after: filter.run_after.map(parse_timestamp).transpose()?,
+        run_before: filter.run_before.map(parse_timestamp).transpose()?,
+        labels: filter.labels.clone(),
+    })
+}
+
+/// Rejects values that do not fit in an AMQP short string
+fn check_short_string(field: &str, value: &str) -> Result<(), Status> {
+    if value.len() > 255 {
+        return Err(Status::invalid_argument(format!(
+            "{} must be at most 255 bytes",
+            field
+        )));
+    }
+    Ok(())
+}
+
+fn parse_properties(properties: &rpc::MessageProperties) -> Result<MessageProperties, Status> {
+    let priority = u8::try_from(properties.priority)
+        .map_err(|_| Status::invalid_argument("priority must be between 0 and 255"))?;
+    let expiration_ms = i64::try_from(properties.expiration_ms)
+        .map_err(|_| Status::invalid_argument("invalid expiration_ms"))?;
+    let delivery_mode = rpc::DeliveryMode::try_from(properties.delivery_mode)
+        .map_err(|_| Status::invalid_argument("invalid delivery_mode"))?;
+    check_short_string("content_type", &properties.content_type)?;
+    check_short_string("message_id", &properties.message_id)?;
+    check_short_string("correlation_id", &properties.correlation_id)?;
+    if properties.headers.keys().any(|key| key.len() > 255) {
+        return Err(Status::invalid_argument(
+            "header names must be at most 255 bytes",
+        ));
+    }
+
+    Ok(MessageProperties {
+        content_type: Some(properties.content_type.clone()).filter(|s| !s.is_empty()),
+        headers: properties.headers.clone(),
+        message_id: Some(properties.message_id.clone()).filter(|s| !s.is_empty()),
+        correlation_id: Some(properties.correlation_id.clone()).filter(|s| !s.is_empty()),
+        priority: Some(priority as i16).filter(|priority| *priority > 0),
+        expiration_ms: Some(expiration_ms).filter(|expiration_ms| *expiration_ms > 0),
+        delivery_mode: match delivery_mode {
+            rpc::DeliveryMode::Unspecified => None,
+            mode => Some(mode as i16),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Utc};
+    use ulid::Ulid;
+
+    use super::{encode_cursor, parse_cursor};
+    use crate::db::TaskCursor;
+
+    #[test]
+    fn cursor_round_trips() {
+        let cursor = TaskCursor {
+            run_at: "2026-10-18T12:34:56.789012Z"
+                .parse::<DateTime<Utc>>()
+                .unwrap(),
+            id: Ulid::new(),
+        };
+        let parsed = parse_cursor(&encode_cursor(cursor)).unwrap();
+        assert_eq!(parsed.run_at, cursor.run_at);
+        assert_eq!(parsed.id, cursor.id);
+    }
+
+    #[test]
+    fn rejects_truncated_cursor() {
+        assert!(parse_cursor(&[0; 23]).is_err());
+        assert!(parse_cursor(&[]).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{anyhow, ensure, Result};
+use async_trait::async_trait;
+use futures::future::{pending, select_all};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    amqp::AmqpDestination,
+    db::DatabaseTask,
+    http::{HttpDestination, HTTP_SINK},
+    kafka::{KafkaDestination, KAFKA_SINK},
+    nats::{NatsDestination, NATS_SINK},
+    prometheus::metrics::{FAILED_TASKS, SUCCESSFUL_TASKS},
+    redis::{RedisDestination, REDIS_SINK},
+};
+
+/// The broker at `AMQP_ADDR`
+pub const DEFAULT_SINK: &str = "amqp";
+
+/// Where a task is delivered when it comes due. AMQP destinations are stored in their own columns,
+/// everything else is stored as JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Destination {
+    #[serde(skip)]
+    Amqp(AmqpDestination),
+    Http(HttpDestination),
+    Redis(RedisDestination),
+    Nats(NatsDestination),
+    Kafka(KafkaDestination),
+}
+
+impl Destination {
+    /// The address tasks are grouped and filtered by
+    pub fn address(&self) -> &str {
+        match self {
+            Destination::Amqp(amqp) => &amqp.routing_key,
+            Destination::Http(http) => &http.url,
+            Destination::Redis(redis) => &redis.key,
+            Destination::Nats(nats) => &nats.subject,
+            Destination::Kafka(kafka) => &kafka.topic,
+        }
+    }
+
+    /// The sink a task is delivered by when it doesn't name one. Every destination kind has a
+    /// built-in sink, registered at startup when the connection it needs is configured.
+    pub fn default_sink(&self) -> &'static str {
+        match self {
+            Destination::Amqp(_) => DEFAULT_SINK,
+            Destination::Http(_) => HTTP_SINK,
+            Destination::Redis(_) => REDIS_SINK,
+            Destination::Nats(_) => NATS_SINK,
+            Destination::Kafka(_) => KAFKA_SINK,
+        }
+    }
+
+    pub fn amqp(&self) -> Option<&AmqpDestination> {
+        match self {
+            Destination::Amqp(amqp) => Some(amqp),
+            _ => None,
+        }
+    }
+
+    /// Returns the JSON form of destinations that aren't stored in the AMQP columns
+    pub fn to_json(&self) -> Result<Option<serde_json::Value>> {
+        match self {
+            Destination::Amqp(_) => Ok(None),
+            _ => Ok(Some(serde_json::to_value(self)?)),
+        }
+    }
+}
+
+/// Delivers due tasks to an external system
+#[async_trait]
+pub trait Sink: Send + Sync {
+    /// Delivers a task, returning once the destination has accepted it
+    async fn deliver(&self, task: &DatabaseTask) -> Result<()>;
+
+    /// Returns true if this sink can deliver tasks to the destination
+    fn accepts(&self, destination: &Destination) -> bool;
+
+    /// Returns false while the sink cannot deliver anything. Tasks for unavailable sinks are
+    /// left alone rather than failed.
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// Waits until the sink is available again
+    async fn wait_available(&self) {}
+}
+
+/// The sinks registered at startup, by name
+pub struct Sinks {
+    sinks: HashMap<String, Arc<dyn Sink>>,
+}
+
+impl Sinks {
+    pub fn new() -> Self {
+        Self {
+            sinks: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, name: &str, sink: Arc<dyn Sink>) -> Result<()> {
+        ensure!(
+            !self.sinks.contains_key(name),
+            "Sink {} is already registered",
+            name
+        );
+        self.sinks.insert(name.to_string(), sink);
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Sink>> {
+        self.sinks.get(name)
+    }
+
+    /// Delivers a task to the sink it targets
+    pub async fn deliver(&self, task: &DatabaseTask) -> Result<()> {
+        let result = match self.get(&task.sink) {
+            Some(sink) => sink.deliver(task).await,
+            None => Err(anyhow!("Unknown sink: {}", task.sink)),
+        };
+        match result {
+            Ok(_) => SUCCESSFUL_TASKS.inc(),
+            Err(_) => FAILED_TASKS.inc(),
+        }
+        result
+    }
+
+    /// Returns the names of the sinks that currently cannot deliver tasks
+    pub fn unavailable(&self) -> Vec<String> {
+        self.sinks
+            .iter()
+            .filter(|(_, sink)| !sink.is_available())
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Waits until any currently unavailable sink becomes available. Never returns if every
+    /// sink is available.
+    pub async fn wait_any_available(&self) {
+        let waiting = self
+            .sinks
+            .values()
+            .filter(|sink| !sink.is_available())
+            .map(|sink| sink.wait_available())
+            .collect::<Vec<_>>();
+        if waiting.is_empty() {
+            pending::<()>().await;
+        } else {
+            select_all(waiting).await;
+        }
+    }
+}
+
+impl Default for Sinks {
+    fn default() -> Self {
+        Self::new()
+    }
+}